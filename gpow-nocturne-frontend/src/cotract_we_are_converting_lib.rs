@@ -2,10 +2,81 @@
 
 #[ink::contract]
 mod nocturne_job {
+    use ink::env::hash::{Blake2x256, HashOutput};
     use ink::env::{account_id, block_timestamp, DefaultEnvironment};
     use ink::prelude::string::String;
-    use ink::prelude::{vec, vec::Vec};
-    use ink::storage::StorageVec;
+    use ink::prelude::vec::Vec;
+    use ink::storage::{Lazy, Mapping};
+
+    /// Default time a worker may hold a task before it can be reclaimed (1 hour)
+    const DEFAULT_LEASE_DURATION: Timestamp = 60 * 60 * 1000;
+
+    /// Default stake a worker must lock to be allowed to claim tasks
+    const DEFAULT_MIN_STAKE: Balance = 1_000_000_000_000;
+
+    /// Default time a worker must wait after registering or claiming before unregistering (1 day)
+    const DEFAULT_WORKER_COOLDOWN: Timestamp = 24 * 60 * 60 * 1000;
+
+    /// Default share of the stake slashed for a failed or abandoned task (10%)
+    const DEFAULT_SLASH_BPS: u32 = 1_000;
+
+    /// Basis points denominator
+    const BPS: Balance = 10_000;
+
+    /// Max number of jobs a listing message scans in one call
+    const MAX_PAGE_SIZE: u32 = 50;
+
+    /// A task held by a worker, as a (job id, task id) pair
+    pub type TaskRef = (u32, u32);
+
+    /// Max number of open job queue entries `claim_first` looks at per queue in one call
+    const MAX_QUEUE_SCAN: u32 = 32;
+
+    /// Number of job priority levels
+    const PRIORITY_LEVELS: usize = 4;
+
+    /// Number of open job queues per priority, one per runtime and one for jobs without requirements
+    const RUNTIME_QUEUES: usize = 4;
+
+    /// Number of open job queues
+    const OPEN_QUEUES: usize = PRIORITY_LEVELS * RUNTIME_QUEUES;
+
+    /// Time a job waits in the queue to gain one priority level (30 minutes)
+    const PRIORITY_AGING: Timestamp = 30 * 60 * 1000;
+
+    /// Version of the storage layout written by this code
+    /// Bump it whenever the layout of a stored job changes: copy the layout being replaced into
+    /// frozen `JobVn` and `TaskVn` structs, point its `JobRecord` variant at them, and add a variant for `Job`
+    const STORAGE_VERSION: u32 = 2;
+
+    /// Default time after the first commitment of a shard before its results may be revealed (10 minutes)
+    const DEFAULT_REVEAL_WINDOW: Timestamp = 10 * 60 * 1000;
+
+    /// Default number of tasks a worker may hold in progress at once
+    const DEFAULT_CONCURRENCY: u32 = 1;
+
+    /// Max number of parent jobs a job may depend on
+    const MAX_PARENTS: usize = 16;
+
+    /// Max number of jobs that may wait for the same job
+    const MAX_DEPENDENTS: usize = 32;
+
+    /// Max number of dependent jobs failed in one call, `notify_dependents` fails the rest
+    const MAX_CASCADE: u32 = 64;
+
+    /// Commitment to a task result, as passed to `commit_result`
+    /// Hash of the result content id, a salt chosen by the worker and the worker account id
+    pub fn result_commitment(cid_result: &str, salt: &Hash, worker: &AccountId) -> Hash {
+        let mut output = <Blake2x256 as HashOutput>::Type::default();
+        ink::env::hash_encoded::<Blake2x256, _>(&(cid_result, salt, worker), &mut output);
+        Hash::from(output)
+    }
+
+    /// Index of the open job queue for a priority level and a runtime slot
+    /// The last slot of each priority holds the jobs without requirements
+    fn open_queue(level: usize, slot: usize) -> u8 {
+        (level * RUNTIME_QUEUES + slot) as u8
+    }
 
     //-- Errors
     /// Errors that can occur upon calling this contract.
@@ -35,6 +106,93 @@ mod nocturne_job {
 
         /// Returned if the caller is already a worker of a job.
         CallerBusy,
+
+        /// Returned if releasing escrowed funds fails.
+        PayoutFailed,
+
+        /// Returned if the lease on a task has already expired.
+        LeaseExpired,
+
+        /// Returned if the job deadline has passed.
+        JobExpired,
+
+        /// Returned if the job still has tasks in progress.
+        TasksInProgress,
+
+        /// Returned if the caller is not a registered worker.
+        NotRegistered,
+
+        /// Returned if the worker stake is below the required minimum.
+        InsufficientStake,
+
+        /// Returned if the worker cooldown has not yet passed.
+        CooldownActive,
+
+        /// Returned if the worker reputation is below the job minimum.
+        ReputationTooLow,
+
+        /// Returned if the worker capabilities do not satisfy the job requirements.
+        RequirementsNotMet,
+
+        /// Returned if the caller is not the owner of the contract.
+        NotContractOwner,
+
+        /// Returned if a configuration value is out of range.
+        InvalidConfig,
+
+        /// Returned if new work is submitted or claimed while the contract is paused.
+        Paused,
+
+        /// Returned if the caller is neither the owner nor the guardian of the contract.
+        NotGuardian,
+
+        /// Returned if the code of the contract could not be replaced.
+        UpgradeFailed,
+
+        /// Returned if the caller does not hold the role the call requires.
+        MissingRole,
+
+        /// Returned if the task has no accepted result waiting for its payout.
+        NoPayout,
+
+        /// Returned if a payout is released, or a job only waiting on payouts is cancelled, before the challenge period has passed.
+        ChallengeActive,
+
+        /// Returned if a result is disputed after its challenge period has passed.
+        ChallengeClosed,
+
+        /// Returned if the payout of a task is frozen by a dispute.
+        Disputed,
+
+        /// Returned if a dispute is resolved for a task that is not disputed.
+        NotDisputed,
+
+        /// Returned if a replicated task is completed without committing to its result first.
+        CommitRequired,
+
+        /// Returned if a worker commits to a result twice.
+        AlreadyCommitted,
+
+        /// Returned if a worker commits after the reveals of the shard opened.
+        CommitClosed,
+
+        /// Returned if a worker reveals a result it never committed to.
+        NotCommitted,
+
+        /// Returned if a result is revealed before every replica committed or the reveal window opened.
+        RevealNotOpen,
+
+        /// Returned if a revealed result does not match its commitment.
+        CommitmentMismatch,
+
+        /// Returned if a specific task is claimed while another worker holds it.
+        TaskTaken,
+
+        /// Returned if a specific task is claimed after it failed or completed.
+        TaskClosed,
+
+        /// Returned if a task is claimed before every parent job has completed.
+        ParentsPending,
     }
     //-- End of Errors
 
@@ -42,7 +200,7 @@ mod nocturne_job {
     /// JobSubmitted event
     /// This event is emitted when a new job is submitted successfully
     /// The event contains the creator of the job and the job id
-    /// The job id is the index of the job in the jobs storage
+    /// The job id is unique and never reused
     #[ink(event)]
     pub struct JobSubmitted {
         /// The creator of the job
@@ -75,7 +233,6 @@ mod nocturne_job {
     /// JobCompleted event
     /// This event is emitted when a job is completed successfully
     /// The event contains the creator of the job and the job id
-    /// The job id is the index of the job in the jobs storage
     /// The event also contains the cid of the result data of the job
     #[ink(event)]
     pub struct JobCompleted {
@@ -94,13 +251,17 @@ mod nocturne_job {
 
     /// TaskClaimed event
     /// This event is emitted when a task is claimed successfully
-    /// The event contains the account id of the worker and the task id
+    /// The event contains the account id of the worker, the job id and the task id
     #[ink(event)]
     pub struct TaskClaimed {
         /// The account id of the worker
         #[ink(topic)]
         worker: AccountId,
 
+        /// The job id
+        #[ink(topic)]
+        job_id: u32,
+
         /// The task id
         /// The task id is the index of the task in the tasks storage
         #[ink(topic)]
@@ -109,7 +270,7 @@ mod nocturne_job {
 
     /// TaskFailed event
     /// This event is emitted when a task fails
-    /// The event contains the account id of the worker and the task id
+    /// The event contains the account id of the worker, the job id and the task id
     #[ink(event)]
     pub struct TaskFailed {
         /// The account id of the worker
@@ -117,647 +278,5064 @@ mod nocturne_job {
         #[ink(topic)]
         worker: AccountId,
 
+        /// The job id
+        #[ink(topic)]
+        job_id: u32,
+
         /// The task id
         #[ink(topic)]
         task_id: u32,
     }
-    //-- End of Events
 
-    //-- Storage
-    /// Enumeration of valid job states.
-    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
-    #[ink::scale_derive(Encode, Decode, TypeInfo)]
-    #[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
-    pub enum JobStatus {
-        /// Job has been created and awaiting processing.
-        Created,
+    /// TaskExpired event
+    /// This event is emitted when an abandoned task is reclaimed after its lease expired
+    /// The event contains the account id of the worker that held the lease, the job id and the task id
+    #[ink(event)]
+    pub struct TaskExpired {
+        /// The account id of the worker
+        #[ink(topic)]
+        worker: AccountId,
 
-        /// Job is currently in progress.
-        InProgress,
+        /// The job id
+        #[ink(topic)]
+        job_id: u32,
 
-        /// Job has failed. No further processing will be attempted.
-        Failed,
+        /// The task id
+        task_id: u32,
+    }
 
-        /// Job has been completed successfully.
-        Completed,
+    /// TaskRejected event
+    /// This event is emitted when a task result disagrees with the result accepted for its shard
+    /// The event contains the account id of the worker, the job id and the task id
+    #[ink(event)]
+    pub struct TaskRejected {
+        /// The account id of the worker
+        #[ink(topic)]
+        worker: AccountId,
+
+        /// The job id
+        #[ink(topic)]
+        job_id: u32,
+
+        /// The task id
+        task_id: u32,
     }
 
-    #[cfg_attr(
-        feature = "std",
-        derive(Debug, PartialEq, ink::storage::traits::StorageLayout)
-    )]
-    #[derive(Clone)]
-    #[ink::scale_derive(Encode, Decode, TypeInfo)]
-    pub struct Task {
-        /// The task ID
-        id: u32,
+    /// TaskPaid event
+    /// This event is emitted when a task's share of the tip is released to its worker
+    /// The event contains the account id of the worker, the job id, the task id and the amount paid
+    #[ink(event)]
+    pub struct TaskPaid {
+        /// The account id of the worker
+        #[ink(topic)]
+        worker: AccountId,
 
-        /// The worker account id
-        worker: Option<AccountId>,
+        /// The job id
+        #[ink(topic)]
+        job_id: u32,
 
-        /// The status of the task
-        status: JobStatus,
+        /// The task id
+        task_id: u32,
 
-        /// The number of retries for the task
-        retries: u32,
+        /// The amount released to the worker
+        amount: Balance,
+    }
 
-        /// The timestamp when the task was created
-        created_at: Timestamp,
+    /// ResultCommitted event
+    /// This event is emitted when a worker commits to the result of a task
+    /// The event contains the account id of the worker, the job id and the task id
+    #[ink(event)]
+    pub struct ResultCommitted {
+        /// The account id of the worker
+        #[ink(topic)]
+        worker: AccountId,
 
-        /// The timestamp when the task was last updated
-        updated_at: Option<Timestamp>,
+        /// The job id
+        #[ink(topic)]
+        job_id: u32,
 
-        /// The timestamp when the task was completed
-        completed_at: Option<Timestamp>,
+        /// The task id
+        task_id: u32,
     }
 
-    impl Task {
-        pub fn new(worker: AccountId) -> Self {
-            Self {
-                id: 0,
-                worker: Some(worker),
-                status: JobStatus::InProgress,
-                retries: 0,
-                created_at: block_timestamp::<DefaultEnvironment>(),
-                updated_at: None,
-                completed_at: None,
-            }
-        }
+    /// JobRunnable event
+    /// This event is emitted when the last parent of a dependent job completes
+    /// The event contains the job id, whose tasks can now be claimed
+    #[ink(event)]
+    pub struct JobRunnable {
+        /// The job id
+        #[ink(topic)]
+        job_id: u32,
+    }
 
-        pub fn default() -> Self {
-            Self {
-                id: 0,
-                worker: None,
-                status: JobStatus::Created,
-                retries: 0,
-                created_at: block_timestamp::<DefaultEnvironment>(),
-                updated_at: None,
-                completed_at: None,
-            }
-        }
+    /// ReduceCreated event
+    /// This event is emitted when every map task of a map-reduce job has an accepted result
+    /// The event contains the job id and the shard of the reduce tasks, which can now be claimed
+    #[ink(event)]
+    pub struct ReduceCreated {
+        /// The job id
+        #[ink(topic)]
+        job_id: u32,
+
+        /// The shard of the reduce tasks
+        shard: u32,
     }
 
-    #[cfg_attr(
-        feature = "std",
-        derive(Debug, PartialEq, ink::storage::traits::StorageLayout)
-    )]
-    #[derive(Clone)]
-    #[ink::scale_derive(Encode, Decode, TypeInfo)]
-    pub struct Job {
+    /// DependencyFailed event
+    /// This event is emitted when a job fails because a job it depends on failed or expired
+    /// The event contains the job id and the id of the parent
+    #[ink(event)]
+    pub struct DependencyFailed {
+        /// The job id
+        #[ink(topic)]
+        job_id: u32,
+
+        /// The id of the parent job that failed
+        parent_id: u32,
+    }
+
+    /// TaskDisputed event
+    /// This event is emitted when the creator of a job disputes the result of a task
+    /// The event contains the creator, the job id, the task id and the content id of the reason
+    #[ink(event)]
+    pub struct TaskDisputed {
         /// The account id of the creator of the job
+        #[ink(topic)]
         creator: AccountId,
 
-        /// The content id of the manifest for the job
-        /// The manifest contains the instructions for the job
-        cid_manifest: String,
+        /// The job id
+        #[ink(topic)]
+        job_id: u32,
 
-        /// The content id of the result manifest for the job
-        cid_results: Option<Vec<String>>,
+        /// The task id
+        task_id: u32,
 
-        /// The tip for the worker
-        /// The tip is the total amount tipped to the worker(s) for completing the job
-        tip: Option<Balance>,
+        /// The content id of the reason for the dispute
+        reason_cid: String,
+    }
 
-        /// List of tasks within the job
-        tasks: Vec<Task>,
+    /// DisputeResolved event
+    /// This event is emitted when an auditor resolves a dispute
+    /// The event contains the job id, the task id, the outcome and the auditor
+    #[ink(event)]
+    pub struct DisputeResolved {
+        /// The job id
+        #[ink(topic)]
+        job_id: u32,
 
-        /// The timestamp when the job was created
-        /// The timestamp is the block number when the job was created
-        created_at: Timestamp,
+        /// The task id
+        task_id: u32,
 
-        /// The timestamp when the job was last updated
-        /// The timestamp is the block number when the job was last updated
-        updated_at: Option<Timestamp>,
+        /// True if the reward was released to the worker, false if the task was reopened
+        released: bool,
 
-        /// The timestamp when the job was completed
-        /// The timestamp is the block number when the job was completed
-        completed_at: Option<Timestamp>,
+        /// The account id of the auditor that resolved the dispute
+        resolved_by: AccountId,
     }
 
-    impl Job {
-        pub fn new(creator: AccountId, cid_manifest: String, task_count: u32) -> Self {
-            let tasks = vec![Task::default(); task_count as usize];
-            let blocktimestamp = block_timestamp::<DefaultEnvironment>();
+    /// JobRefunded event
+    /// This event is emitted when the unspent escrow of a job is returned to its creator
+    /// The event contains the creator of the job, the job id and the amount refunded
+    #[ink(event)]
+    pub struct JobRefunded {
+        /// The creator of the job
+        #[ink(topic)]
+        creator: AccountId,
 
-            Self {
-                creator,
-                cid_manifest,
-                cid_results: None,
-                tip: None,
-                tasks,
-                created_at: blocktimestamp,
-                updated_at: Some(blocktimestamp),
-                completed_at: None,
-            }
-        }
+        /// The job id
+        #[ink(topic)]
+        job_id: u32,
 
-        pub fn default() -> Self {
-            let blocktimestamp = block_timestamp::<DefaultEnvironment>();
+        /// The amount returned to the creator
+        amount: Balance,
+    }
 
-            Self {
-                creator: account_id::<ink::env::DefaultEnvironment>(),
-                cid_manifest: Default::default(),
-                cid_results: None,
-                tip: None,
-                tasks: Default::default(),
-                created_at: blocktimestamp,
-                updated_at: Some(blocktimestamp),
-                completed_at: None,
-            }
-        }
+    /// WorkerRegistered event
+    /// This event is emitted when a worker registers or tops up its stake
+    /// The event contains the account id of the worker and its total stake
+    #[ink(event)]
+    pub struct WorkerRegistered {
+        /// The account id of the worker
+        #[ink(topic)]
+        worker: AccountId,
 
-        pub fn get_job_status(&self) -> JobStatus {
-            // If no tasks, return Failed
-            // Should "never" happen
-            if self.tasks.is_empty() {
-                return JobStatus::Failed;
-            }
+        /// The total stake locked by the worker
+        stake: Balance,
+    }
+
+    /// WorkerUnregistered event
+    /// This event is emitted when a worker leaves the registry and its stake is returned
+    /// The event contains the account id of the worker and the stake returned
+    #[ink(event)]
+    pub struct WorkerUnregistered {
+        /// The account id of the worker
+        #[ink(topic)]
+        worker: AccountId,
+
+        /// The stake returned to the worker
+        stake: Balance,
+    }
+
+    /// WorkerSlashed event
+    /// This event is emitted when part of a worker's stake is slashed for a failed or abandoned task
+    /// The slashed amount is paid to the creator of the job
+    #[ink(event)]
+    pub struct WorkerSlashed {
+        /// The account id of the worker
+        #[ink(topic)]
+        worker: AccountId,
+
+        /// The job id
+        #[ink(topic)]
+        job_id: u32,
+
+        /// The task id
+        task_id: u32,
+
+        /// The amount slashed from the stake
+        amount: Balance,
+    }
+
+    /// ConfigChanged event
+    /// This event is emitted when the contract owner changes a setting
+    /// The event contains the account that made the change and the new setting
+    #[ink(event)]
+    pub struct ConfigChanged {
+        /// The account id of the caller that changed the setting
+        #[ink(topic)]
+        changed_by: AccountId,
+
+        /// The new value of the setting
+        setting: Setting,
+    }
+
+    /// RoleGranted event
+    /// This event is emitted when the contract owner grants a role
+    /// The event contains the account, the role and the account that granted it
+    #[ink(event)]
+    pub struct RoleGranted {
+        /// The account id of the account that received the role
+        #[ink(topic)]
+        account: AccountId,
+
+        /// The role granted
+        role: Role,
+
+        /// The account id of the owner that granted the role
+        granted_by: AccountId,
+    }
+
+    /// RoleRevoked event
+    /// This event is emitted when the contract owner revokes a role
+    /// The event contains the account, the role and the account that revoked it
+    #[ink(event)]
+    pub struct RoleRevoked {
+        /// The account id of the account that lost the role
+        #[ink(topic)]
+        account: AccountId,
+
+        /// The role revoked
+        role: Role,
+
+        /// The account id of the owner that revoked the role
+        revoked_by: AccountId,
+    }
+
+    /// ContractPaused event
+    /// This event is emitted when the contract is paused
+    /// The event contains the account that paused the contract
+    #[ink(event)]
+    pub struct ContractPaused {
+        /// The account id of the owner or guardian that paused the contract
+        #[ink(topic)]
+        by: AccountId,
+    }
+
+    /// CodeUpgraded event
+    /// This event is emitted when the code of the contract is replaced
+    /// The event contains the hash of the new code
+    #[ink(event)]
+    pub struct CodeUpgraded {
+        /// The code hash the contract now runs
+        code_hash: Hash,
+    }
+
+    /// StorageMigrated event
+    /// This event is emitted once every stored job has been rewritten to the current layout
+    /// The event contains the new storage version
+    #[ink(event)]
+    pub struct StorageMigrated {
+        /// The storage version the contract was migrated to
+        version: u32,
+    }
+
+    /// ContractUnpaused event
+    /// This event is emitted when the contract is unpaused
+    /// The event contains the account that unpaused the contract
+    #[ink(event)]
+    pub struct ContractUnpaused {
+        /// The account id of the owner or guardian that unpaused the contract
+        #[ink(topic)]
+        by: AccountId,
+    }
+    //-- End of Events
+
+    //-- Storage
+    /// Enumeration of valid job states.
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    #[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+    pub enum JobStatus {
+        /// Job has been created and awaiting processing.
+        Created,
+
+        /// Job is currently in progress.
+        InProgress,
+
+        /// Job has failed. No further processing will be attempted.
+        Failed,
+
+        /// Job has been completed successfully.
+        Completed,
+
+        /// Job deadline passed before all tasks were completed.
+        Expired,
+    }
+
+    /// Contract setting changed by the owner.
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    pub enum Setting {
+        /// Max retries for a task.
+        MaxRetries(u32),
+
+        /// Max tasks a job may have.
+        MaxTasks(u32),
+
+        /// Owner of the contract, None once ownership is renounced.
+        Owner(Option<AccountId>),
+
+        /// Guardian allowed to pause the contract, besides the owner.
+        Guardian(Option<AccountId>),
+
+        /// Whether only accounts with the Worker role may claim tasks.
+        Permissioned(bool),
+
+        /// Time the creator has to dispute a result before it is paid.
+        ChallengePeriod(Timestamp),
+
+        /// Time after the first commitment of a shard before its results may be revealed.
+        RevealWindow(Timestamp),
+
+        /// Tasks a worker may hold in progress at once, unless it declared its own capacity.
+        DefaultConcurrency(u32),
+    }
+
+    /// Named roles the contract owner can grant to accounts.
+    /// The owner passes every role check except Worker.
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    #[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+    pub enum Role {
+        /// May pause the contract, reclaim expired tasks and adjust limits.
+        Operator,
+
+        /// May claim tasks while the contract is permissioned.
+        Worker,
+
+        /// May resolve disputes over task results.
+        Auditor,
+    }
+
+    /// Scheduling priority of a job.
+    #[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    #[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+    pub enum Priority {
+        /// Job runs when nothing more pressing is waiting.
+        Low,
+
+        /// Job runs in the order jobs became claimable.
+        #[default]
+        Normal,
+
+        /// Job runs ahead of normal jobs.
+        High,
+
+        /// Job runs ahead of everything else.
+        Urgent,
+    }
+
+    /// Runtime a job is packaged for.
+    #[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    #[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+    pub enum Runtime {
+        /// Job runs as a Docker container.
+        #[default]
+        Docker,
+
+        /// Job runs on a Kubernetes cluster.
+        Kubernetes,
+
+        /// Job runs on a Petals swarm.
+        Petals,
+    }
+
+    /// Shape of the work of a job.
+    #[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    #[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+    pub enum JobKind {
+        /// Independent tasks, the job results are the task results.
+        #[default]
+        Batch,
+
+        /// Map tasks followed by a reduce task aggregating their results.
+        MapReduce,
+    }
+
+    /// Resources and placement a job needs from its workers
+    #[cfg_attr(
+        feature = "std",
+        derive(Debug, PartialEq, ink::storage::traits::StorageLayout)
+    )]
+    #[derive(Clone, Default)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    pub struct JobRequirements {
+        /// The runtime the job is packaged for
+        pub runtime: Runtime,
+
+        /// The minimum GPU memory in MiB
+        pub min_gpu_memory: u32,
+
+        /// The minimum number of CPU cores
+        pub min_cpu_cores: u32,
+
+        /// The minimum RAM in MiB
+        pub min_memory: u32,
+
+        /// The regions the job may run in
+        /// Any region is accepted if empty
+        pub regions: Vec<String>,
+    }
+
+    /// Resources and placement a worker offers
+    #[cfg_attr(
+        feature = "std",
+        derive(Debug, PartialEq, ink::storage::traits::StorageLayout)
+    )]
+    #[derive(Clone, Default)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    pub struct Capabilities {
+        /// The runtimes the worker can run
+        pub runtimes: Vec<Runtime>,
+
+        /// The GPU memory in MiB
+        pub gpu_memory: u32,
+
+        /// The number of CPU cores
+        pub cpu_cores: u32,
+
+        /// The RAM in MiB
+        pub memory: u32,
+
+        /// The region the worker runs in
+        pub region: Option<String>,
+
+        /// The number of tasks the worker can run at once, e.g. one per GPU
+        /// The contract default applies if 0
+        pub concurrency: u32,
+    }
+
+    impl Capabilities {
+        /// Returns true if the worker capabilities meet every job requirement
+        pub fn satisfies(&self, requirements: &JobRequirements) -> bool {
+            self.runtimes.contains(&requirements.runtime)
+                && self.gpu_memory >= requirements.min_gpu_memory
+                && self.cpu_cores >= requirements.min_cpu_cores
+                && self.memory >= requirements.min_memory
+                && (requirements.regions.is_empty()
+                    || self
+                        .region
+                        .as_ref()
+                        .is_some_and(|region| requirements.regions.contains(region)))
+        }
+    }
+
+    /// Optional settings of a job, given on submit
+    #[cfg_attr(feature = "std", derive(Debug, PartialEq))]
+    #[derive(Clone, Default)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    pub struct JobOptions {
+        /// The timestamp the job must be completed by
+        /// Unclaimed tasks expire once it has passed
+        pub deadline: Option<Timestamp>,
+
+        /// The minimum reputation a worker needs to claim tasks of the job
+        pub min_reputation: u32,
+
+        /// The scheduling priority of the job in `claim_first`
+        pub priority: Priority,
+
+        /// The resources and placement the job needs from its workers
+        /// Any worker may claim the job if None
+        pub requirements: Option<JobRequirements>,
+
+        /// The number of distinct workers each task runs on
+        /// Every task runs once if 0
+        pub replicas: u32,
+
+        /// The number of matching results needed to accept a task
+        /// Must be a majority of the replicas, all replicas must agree if 0
+        pub quorum: u32,
+
+        /// The ids of the jobs that must complete before tasks of the job can be claimed
+        /// The job fails if one of them fails
+        pub parents: Vec<u32>,
+
+        /// The shape of the job
+        /// The tasks of a map-reduce job are its map tasks, the reduce task is created once they are done
+        pub kind: JobKind,
+    }
+
+    #[cfg_attr(
+        feature = "std",
+        derive(Debug, PartialEq, ink::storage::traits::StorageLayout)
+    )]
+    #[derive(Clone)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    pub struct Task {
+        /// The task ID
+        /// The task ID is the index of the task within its job
+        id: u32,
+
+        /// The worker account id
+        worker: Option<AccountId>,
+
+        /// The status of the task
+        status: JobStatus,
+
+        /// The number of retries for the task
+        retries: u32,
+
+        /// The timestamp when the task was created
+        created_at: Timestamp,
+
+        /// The timestamp when the task was last updated
+        updated_at: Option<Timestamp>,
+
+        /// The timestamp when the task was completed
+        completed_at: Option<Timestamp>,
+
+        /// The share of the job tip paid to the worker on completion
+        reward: Balance,
+
+        /// The timestamp after which the worker's claim on the task may be reclaimed
+        lease_expires_at: Option<Timestamp>,
+
+        /// The content id of the result produced by the task
+        cid_result: Option<String>,
+
+        /// The shard of the job the task is a replica of
+        shard: u32,
+
+        /// True once the reward of the task has been paid to its worker
+        paid: bool,
+
+        /// True if the result of the task disagreed with the result accepted for its shard
+        rejected: bool,
+
+        /// The timestamp after which the reward of an accepted result may be released
+        /// Set while the result waits out the challenge period, None once paid
+        payable_at: Option<Timestamp>,
+
+        /// The content id of the reason the creator disputed the result
+        /// The payout is frozen until an auditor resolves the dispute
+        dispute: Option<String>,
+
+        /// The content ids the task takes as input
+        /// The map results for the reduce task of a map-reduce job, empty otherwise
+        cid_inputs: Vec<String>,
+
+        /// The hash the worker committed to before revealing its result
+        commitment: Option<Hash>,
+
+        /// The timestamp when the worker committed to its result
+        committed_at: Option<Timestamp>,
+    }
+
+    impl Task {
+        pub fn new(worker: AccountId) -> Self {
+            Self {
+                id: 0,
+                worker: Some(worker),
+                status: JobStatus::InProgress,
+                retries: 0,
+                created_at: block_timestamp::<DefaultEnvironment>(),
+                updated_at: None,
+                completed_at: None,
+                reward: 0,
+                lease_expires_at: None,
+                cid_result: None,
+                shard: 0,
+                paid: false,
+                rejected: false,
+                payable_at: None,
+                dispute: None,
+                cid_inputs: Vec::new(),
+                commitment: None,
+                committed_at: None,
+            }
+        }
+
+        pub fn default() -> Self {
+            Self {
+                id: 0,
+                worker: None,
+                status: JobStatus::Created,
+                retries: 0,
+                created_at: block_timestamp::<DefaultEnvironment>(),
+                updated_at: None,
+                completed_at: None,
+                reward: 0,
+                lease_expires_at: None,
+                cid_result: None,
+                shard: 0,
+                paid: false,
+                rejected: false,
+                payable_at: None,
+                dispute: None,
+                cid_inputs: Vec::new(),
+                commitment: None,
+                committed_at: None,
+            }
+        }
+
+        /// Create an unclaimed task with the given id
+        pub fn with_id(id: u32) -> Self {
+            Self {
+                id,
+                shard: id,
+                ..Self::default()
+            }
+        }
+
+        /// Returns true if the task is in progress and its lease ran out before `now`
+        pub fn is_expired(&self, now: Timestamp) -> bool {
+            self.status == JobStatus::InProgress
+                && matches!(self.lease_expires_at, Some(expires_at) if now > expires_at)
+        }
+
+        /// Release the task from its worker after a failure or an expired lease
+        /// The task goes back to Created, or to Failed once it has used up its retries
+        /// Returns the new status of the task
+        pub fn release(&mut self, max_retries: u32) -> Result<JobStatus, Error> {
+            self.retries = self.retries.checked_add(1).ok_or(Error::UpdateFailed)?;
+            self.lease_expires_at = None;
+            self.commitment = None;
+            self.committed_at = None;
+            self.updated_at = Some(block_timestamp::<DefaultEnvironment>());
+
+            if self.retries >= max_retries {
+                self.status = JobStatus::Failed;
+            } else {
+                self.worker = None;
+                self.status = JobStatus::Created;
+            }
+
+            Ok(self.status)
+        }
+
+        /// Put a task with a rejected result back up for claiming
+        /// The result, the pending payout and the dispute are dropped
+        pub fn reopen(&mut self) {
+            self.worker = None;
+            self.status = JobStatus::Created;
+            self.cid_result = None;
+            self.completed_at = None;
+            self.lease_expires_at = None;
+            self.payable_at = None;
+            self.dispute = None;
+            self.commitment = None;
+            self.committed_at = None;
+            self.updated_at = Some(block_timestamp::<DefaultEnvironment>());
+        }
+
+        /// Returns true if the replica was closed without a result
+        pub fn is_closed(&self) -> bool {
+            self.status == JobStatus::Completed && self.cid_result.is_none()
+        }
+
+        /// Close a replica its shard no longer needs, without a result
+        /// The replica counts as done and its worker is released from it
+        pub fn close(&mut self) {
+            let now = block_timestamp::<DefaultEnvironment>();
+            self.worker = None;
+            self.status = JobStatus::Completed;
+            self.completed_at = Some(now);
+            self.lease_expires_at = None;
+            self.commitment = None;
+            self.committed_at = None;
+            self.updated_at = Some(now);
+        }
+    }
+
+    #[cfg_attr(
+        feature = "std",
+        derive(Debug, PartialEq, ink::storage::traits::StorageLayout)
+    )]
+    #[derive(Clone)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    pub struct Job {
+        /// The job ID
+        id: u32,
+
+        /// The account id of the creator of the job
+        creator: AccountId,
+
+        /// The content id of the manifest for the job
+        /// The manifest contains the instructions for the job
+        cid_manifest: String,
+
+        /// The content ids of the accepted results of the job, ordered by shard
+        /// Set once every task of the job has completed
+        /// For a map-reduce job the result of the reduce task comes last
+        cid_results: Option<Vec<String>>,
+
+        /// The tip for the worker
+        /// The tip is the total amount tipped to the worker(s) for completing the job
+        tip: Option<Balance>,
+
+        /// The part of the tip still held in escrow by the contract
+        escrow: Balance,
+
+        /// List of tasks within the job
+        tasks: Vec<Task>,
+
+        /// The timestamp when the job was created
+        /// The timestamp is the block number when the job was created
+        created_at: Timestamp,
+
+        /// The timestamp when the job was last updated
+        /// The timestamp is the block number when the job was last updated
+        updated_at: Option<Timestamp>,
+
+        /// The timestamp when the job was completed
+        /// The timestamp is the block number when the job was completed
+        completed_at: Option<Timestamp>,
+
+        /// The timestamp the job must be completed by
+        /// Unclaimed tasks can no longer be claimed once the deadline has passed
+        deadline: Option<Timestamp>,
+
+        /// The minimum reputation a worker needs to claim tasks of the job
+        min_reputation: u32,
+
+        /// The scheduling priority of the job
+        priority: Priority,
+
+        /// The resources and placement the job needs from its workers
+        requirements: Option<JobRequirements>,
+
+        /// The number of tasks run for each shard of the job, by distinct workers
+        /// The replicas of a shard are stored next to each other
+        replicas: u32,
+
+        /// The number of matching results needed to accept a shard
+        quorum: u32,
+
+        /// The ids of the jobs that must complete before tasks of the job can be claimed
+        parents: Vec<u32>,
+
+        /// The number of parent jobs that have not completed yet
+        pending_parents: u32,
+
+        /// The shape of the job
+        kind: JobKind,
+
+        /// The share of the tip kept in escrow for each reduce task of a map-reduce job
+        reduce_reward: Balance,
+
+        /// The shard of the reduce tasks of a map-reduce job, once they have been created
+        reduce_shard: Option<u32>,
+
+        /// Whether the creator cancelled the job
+        /// A cancelled job is failed, even if its remaining tasks all completed
+        cancelled: bool,
+    }
+
+    /// Outcome of settling a shard after one of its tasks completed
+    #[derive(Default)]
+    pub struct Settlement {
+        /// Tasks whose result matches the accepted result, with their worker and reward
+        pub paid: Vec<(u32, AccountId, Balance)>,
+
+        /// Tasks whose result disagrees with the accepted result, with their worker and reward
+        pub rejected: Vec<(u32, AccountId, Balance)>,
+
+        /// Tasks closed unfinished as the shard no longer needs them, with their worker if claimed and reward
+        pub closed: Vec<(u32, Option<AccountId>, Balance)>,
+
+        /// True if every task of the shard finished without reaching the quorum
+        pub failed: bool,
+    }
+
+    impl Job {
+        pub fn new(creator: AccountId, cid_manifest: String, task_count: u32) -> Self {
+            let tasks = (0..task_count).map(Task::with_id).collect();
+            let blocktimestamp = block_timestamp::<DefaultEnvironment>();
+
+            Self {
+                id: 0,
+                creator,
+                cid_manifest,
+                cid_results: None,
+                tip: None,
+                escrow: 0,
+                tasks,
+                created_at: blocktimestamp,
+                updated_at: Some(blocktimestamp),
+                completed_at: None,
+                deadline: None,
+                min_reputation: 0,
+                priority: Priority::Normal,
+                requirements: None,
+                replicas: 1,
+                quorum: 1,
+                parents: Vec::new(),
+                pending_parents: 0,
+                kind: JobKind::Batch,
+                reduce_reward: 0,
+                reduce_shard: None,
+                cancelled: false,
+            }
+        }
+
+        pub fn default() -> Self {
+            let blocktimestamp = block_timestamp::<DefaultEnvironment>();
+
+            Self {
+                id: 0,
+                creator: account_id::<ink::env::DefaultEnvironment>(),
+                cid_manifest: Default::default(),
+                cid_results: None,
+                tip: None,
+                escrow: 0,
+                tasks: Default::default(),
+                created_at: blocktimestamp,
+                updated_at: Some(blocktimestamp),
+                completed_at: None,
+                deadline: None,
+                min_reputation: 0,
+                priority: Priority::Normal,
+                requirements: None,
+                replicas: 1,
+                quorum: 1,
+                parents: Vec::new(),
+                pending_parents: 0,
+                kind: JobKind::Batch,
+                reduce_reward: 0,
+                reduce_shard: None,
+                cancelled: false,
+            }
+        }
+
+        /// Returns true if the job has a deadline and it passed before `now`
+        pub fn is_expired(&self, now: Timestamp) -> bool {
+            matches!(self.deadline, Some(deadline) if now > deadline)
+        }
+
+        /// Split the tip across the tasks of the job
+        /// Each task gets an equal share, the remainder of the division goes to the first task
+        /// A map-reduce job keeps a share for each of its reduce tasks until they are created
+        /// The whole tip is placed in escrow until the tasks are completed
+        pub fn split_tip(&mut self) {
+            let tip = self.tip.unwrap_or(0);
+            let reduce_tasks = match self.kind {
+                JobKind::Batch => 0,
+                JobKind::MapReduce => self.replicas,
+            };
+            let task_count =
+                (self.tasks.len() as Balance).saturating_add(Balance::from(reduce_tasks));
+            if task_count == 0 {
+                return;
+            }
+
+            let share = tip / task_count;
+            let remainder = tip % task_count;
+            for task in self.tasks.iter_mut() {
+                task.reward = share;
+            }
+            if let Some(task) = self.tasks.first_mut() {
+                task.reward = share.saturating_add(remainder);
+            }
+            if reduce_tasks > 0 {
+                self.reduce_reward = share;
+            }
+
+            self.escrow = tip;
+        }
+
+        /// Create the reduce tasks of a map-reduce job once every map shard has an accepted result
+        /// and none of the map results may still be disputed
+        /// The reduce tasks form a new shard, take the map results as input and get the share of the tip kept for them
+        /// Returns the reduce shard if it was created
+        pub fn start_reduce(&mut self) -> Option<u32> {
+            if self.kind != JobKind::MapReduce
+                || self.cancelled
+                || self.reduce_shard.is_some()
+                || self.tasks.iter().any(|task| task.payable_at.is_some())
+            {
+                return None;
+            }
+
+            let inputs = self.results()?;
+            let shard = inputs.len() as u32;
+            let first_id = self.tasks.len() as u32;
+            for id in first_id..first_id.saturating_add(self.replicas) {
+                self.tasks.push(Task {
+                    shard,
+                    reward: self.reduce_reward,
+                    cid_inputs: inputs.clone(),
+                    ..Task::with_id(id)
+                });
+            }
+            self.reduce_shard = Some(shard);
+
+            Some(shard)
+        }
+
+        /// Whether the job may still complete
+        /// A failed job never does, nor does an expired job with tasks left that can no longer be claimed
+        pub fn can_complete(&self) -> bool {
+            match self.get_job_status() {
+                JobStatus::Failed => false,
+                JobStatus::Expired => !self
+                    .tasks
+                    .iter()
+                    .any(|task| task.status == JobStatus::Created),
+                _ => true,
+            }
+        }
+
+        /// Mark every task that has not been completed as failed
+        pub fn fail_remaining(&mut self) {
+            for task in self
+                .tasks
+                .iter_mut()
+                .filter(|task| task.status != JobStatus::Completed)
+            {
+                task.status = JobStatus::Failed;
+            }
+        }
+
+        /// Take the escrow left for tasks that never completed
+        /// Rewards of accepted results still waiting for their payout stay in escrow
+        /// Returns the amount to refund to the creator
+        pub fn take_refund(&mut self) -> Balance {
+            let owed = self
+                .tasks
+                .iter()
+                .filter(|task| task.payable_at.is_some())
+                .map(|task| task.reward)
+                .sum();
+            let refund = self.escrow.saturating_sub(owed);
+            self.escrow = self.escrow.saturating_sub(refund);
+
+            refund
+        }
+
+        /// Mark the accepted result of a task as paid
+        /// Returns the reward released from escrow
+        pub fn pay_task(&mut self, task_id: u32) -> Result<Balance, Error> {
+            let task = self
+                .tasks
+                .get_mut(task_id as usize)
+                .ok_or(Error::UpdateFailed)?;
+            task.paid = true;
+            task.payable_at = None;
+            self.escrow = self
+                .escrow
+                .checked_sub(task.reward)
+                .ok_or(Error::PayoutFailed)?;
+
+            Ok(task.reward)
+        }
+
+        pub fn get_job_status(&self) -> JobStatus {
+            // If no tasks or cancelled, return Failed
+            // No tasks should "never" happen
+            if self.tasks.is_empty() || self.cancelled {
+                return JobStatus::Failed;
+            }
+
+            // Aggregate task status to determine job status
+            // If any task is failed, job is failed
+            // If all tasks are completed, job is completed
+            // If the deadline passed, job is expired
+            // If any task is in progress and none failed, job is in progress
+            // If all tasks are created, job is created
+            // A map-reduce job is in progress until its reduce tasks exist
+            // A job with results that may still be disputed is in progress
+            let mut has_in_progress = false;
+            let mut has_created = false;
+
+            for task in self.tasks.iter() {
+                if task.status == JobStatus::Failed {
+                    return JobStatus::Failed;
+                } else if task.status == JobStatus::InProgress {
+                    has_in_progress = true;
+                } else if task.status == JobStatus::Created {
+                    has_created = true;
+                }
+            }
+
+            if (has_in_progress || has_created)
+                && self.is_expired(block_timestamp::<DefaultEnvironment>())
+            {
+                JobStatus::Expired
+            } else if has_in_progress {
+                JobStatus::InProgress
+            } else if has_created {
+                JobStatus::Created
+            } else if (self.kind == JobKind::MapReduce && self.reduce_shard.is_none())
+                || self.tasks.iter().any(|task| task.payable_at.is_some())
+            {
+                JobStatus::InProgress
+            } else {
+                JobStatus::Completed
+            }
+        }
+
+        /// Claim a task
+        /// If job has available tasks, claim the first available task
+        /// The worker holds a lease on the task for `lease_duration`
+        /// Returns an error if no tasks are available or the job has expired
+        pub fn claim_task(
+            &mut self,
+            worker: AccountId,
+            lease_duration: Timestamp,
+        ) -> Result<Task, Error> {
+            let now = block_timestamp::<DefaultEnvironment>();
+
+            if self.is_expired(now) {
+                return Err(Error::JobExpired);
+            }
+
+            // Claim first "Created" task of a shard the worker holds no replica of
+            let index = self.claimable_task(worker).ok_or(Error::ClaimFailed)?;
+            self.claim_task_at(index as u32, worker, lease_duration)
+        }
+
+        /// Claim a specific task
+        /// The worker holds a lease on the task for `lease_duration`
+        /// Returns an error if the task is held by another worker, has failed or completed,
+        /// the worker already runs a replica of its shard, the job waits for its parents or has expired
+        pub fn claim_task_at(
+            &mut self,
+            task_id: u32,
+            worker: AccountId,
+            lease_duration: Timestamp,
+        ) -> Result<Task, Error> {
+            let now = block_timestamp::<DefaultEnvironment>();
+
+            if self.is_expired(now) {
+                return Err(Error::JobExpired);
+            }
+
+            if self.pending_parents > 0 {
+                return Err(Error::ParentsPending);
+            }
+
+            let task = self.tasks.get(task_id as usize).ok_or(Error::ClaimFailed)?;
+            match task.status {
+                JobStatus::Created => {}
+                JobStatus::InProgress => return Err(Error::TaskTaken),
+                JobStatus::Failed | JobStatus::Completed | JobStatus::Expired => {
+                    return Err(Error::TaskClosed)
+                }
+            }
+
+            if self.tasks[self.shard_range(task.shard)]
+                .iter()
+                .any(|replica| replica.worker == Some(worker))
+            {
+                return Err(Error::ClaimFailed);
+            }
+
+            let task = &mut self.tasks[task_id as usize];
+            task.worker = Some(worker);
+            task.status = JobStatus::InProgress;
+            task.updated_at = Some(now);
+            task.lease_expires_at = Some(now.saturating_add(lease_duration));
+
+            Ok(task.clone())
+        }
+
+        /// Index of the first unclaimed task the worker may claim
+        /// A worker runs at most one replica of each shard
+        pub fn claimable_task(&self, worker: AccountId) -> Option<usize> {
+            self.tasks.iter().position(|task| {
+                task.status == JobStatus::Created
+                    && !self.tasks[self.shard_range(task.shard)]
+                        .iter()
+                        .any(|replica| replica.worker == Some(worker))
+            })
+        }
+
+        /// Positions of the replicas of a shard within the tasks of the job
+        pub fn shard_range(&self, shard: u32) -> core::ops::Range<usize> {
+            let start = shard.saturating_mul(self.replicas) as usize;
+            start
+                ..start
+                    .saturating_add(self.replicas as usize)
+                    .min(self.tasks.len())
+        }
+
+        /// The result reported by at least `quorum` completed replicas of a shard
+        /// Replicas closed without a result don't take part, the quorum is capped at the replicas left
+        /// The quorum is a majority of the replicas taking part, so at most one result can reach it
+        pub fn accepted_result(&self, shard: u32) -> Option<String> {
+            let replicas = &self.tasks[self.shard_range(shard)];
+            let quorum = replicas
+                .iter()
+                .filter(|task| !task.is_closed())
+                .count()
+                .min(self.quorum as usize);
+            let results = || {
+                replicas
+                    .iter()
+                    .filter(|task| task.status == JobStatus::Completed)
+                    .filter_map(|task| task.cid_result.as_ref())
+            };
+
+            results()
+                .find(|&cid| results().filter(|&other| other == cid).count() >= quorum)
+                .cloned()
+        }
+
+        /// Returns true if the replicas of a shard may reveal their results at `now`
+        /// Reveals open once every replica has committed, or `reveal_window` after the first commitment
+        /// Once open they stay open, commitments are then closed
+        pub fn reveal_open(&self, shard: u32, now: Timestamp, reveal_window: Timestamp) -> bool {
+            let replicas = &self.tasks[self.shard_range(shard)];
+
+            self.reveal_started(shard)
+                || replicas
+                    .iter()
+                    .filter(|task| !task.is_closed())
+                    .all(|task| task.commitment.is_some())
+                || replicas
+                    .iter()
+                    .filter_map(|task| task.committed_at)
+                    .min()
+                    .is_some_and(|first| now >= first.saturating_add(reveal_window))
+        }
+
+        /// Returns true if a replica of the shard has revealed its result
+        pub fn reveal_started(&self, shard: u32) -> bool {
+            self.tasks[self.shard_range(shard)]
+                .iter()
+                .any(|task| task.cid_result.is_some())
+        }
+
+        /// The accepted results of the job, ordered by shard
+        /// None until every shard has an accepted result
+        pub fn results(&self) -> Option<Vec<String>> {
+            let shards = (self.tasks.len() as u32)
+                .checked_div(self.replicas)
+                .unwrap_or(0);
+            (0..shards)
+                .map(|shard| self.accepted_result(shard))
+                .collect()
+        }
+
+        /// Settle the completed replicas of a shard
+        /// Once the reveals of a replicated shard opened, replicas that did not commit are closed
+        /// and the shard settles on the replicas that did
+        /// Once a result reaches the quorum, replicas reporting it are paid and the others rejected
+        /// With `payable_at` set, replicas reporting it wait until then for their payout instead
+        /// Replicas still unclaimed or in progress are then closed, the shard no longer needs them
+        /// Rewards of paid, rejected and closed replicas are released from escrow
+        /// Replicas that all finished without reaching the quorum fail the shard
+        pub fn settle_shard(
+            &mut self,
+            shard: u32,
+            payable_at: Option<Timestamp>,
+            now: Timestamp,
+            reveal_window: Timestamp,
+        ) -> Result<Settlement, Error> {
+            let range = self.shard_range(shard);
+            let mut settlement = Settlement::default();
+
+            if self.replicas > 1 && self.reveal_open(shard, now, reveal_window) {
+                for task in self.tasks[range.clone()].iter_mut().filter(|task| {
+                    matches!(task.status, JobStatus::Created | JobStatus::InProgress)
+                        && task.commitment.is_none()
+                }) {
+                    self.escrow = self
+                        .escrow
+                        .checked_sub(task.reward)
+                        .ok_or(Error::PayoutFailed)?;
+                    settlement.closed.push((task.id, task.worker, task.reward));
+                    task.close();
+                }
+            }
+
+            let Some(accepted) = self.accepted_result(shard) else {
+                let replicas = &mut self.tasks[range.clone()];
+                if replicas
+                    .iter()
+                    .all(|task| task.status == JobStatus::Completed)
+                {
+                    for task in replicas.iter_mut() {
+                        task.status = JobStatus::Failed;
+                    }
+                    settlement.failed = true;
+                }
+
+                return Ok(settlement);
+            };
+
+            for task in self.tasks[range.clone()].iter_mut().filter(|task| {
+                task.status == JobStatus::Completed
+                    && task.cid_result.is_some()
+                    && !task.paid
+                    && !task.rejected
+                    && task.payable_at.is_none()
+            }) {
+                let Some(worker) = task.worker else {
+                    continue;
+                };
+
+                let matches = task.cid_result.as_ref() == Some(&accepted);
+                if matches && payable_at.is_some() {
+                    task.payable_at = payable_at;
+                    continue;
+                }
+
+                self.escrow = self
+                    .escrow
+                    .checked_sub(task.reward)
+                    .ok_or(Error::PayoutFailed)?;
+                if matches {
+                    task.paid = true;
+                    settlement.paid.push((task.id, worker, task.reward));
+                } else {
+                    task.rejected = true;
+                    settlement.rejected.push((task.id, worker, task.reward));
+                }
+            }
+
+            for task in self.tasks[range]
+                .iter_mut()
+                .filter(|task| matches!(task.status, JobStatus::Created | JobStatus::InProgress))
+            {
+                self.escrow = self
+                    .escrow
+                    .checked_sub(task.reward)
+                    .ok_or(Error::PayoutFailed)?;
+                settlement.closed.push((task.id, task.worker, task.reward));
+                task.close();
+            }
+
+            Ok(settlement)
+        }
+
+        /// Lightweight view of the job without its tasks
+        pub fn summary(&self) -> JobSummary {
+            JobSummary {
+                id: self.id,
+                creator: self.creator,
+                cid_manifest: self.cid_manifest.clone(),
+                status: self.get_job_status(),
+                task_count: self.tasks.len() as u32,
+                tip: self.tip,
+                escrow: self.escrow,
+                created_at: self.created_at,
+                completed_at: self.completed_at,
+                deadline: self.deadline,
+                priority: self.priority,
+                pending_parents: self.pending_parents,
+                kind: self.kind,
+            }
+        }
+
+        /// Priority of the job in the queue at `now`
+        /// A job gains one level for every `PRIORITY_AGING` since it was submitted
+        /// Aging ranks the jobs at the front of the open queues, it does not move a job up within its queue
+        pub fn effective_priority(&self, now: Timestamp) -> u64 {
+            let waited = now.saturating_sub(self.created_at) / PRIORITY_AGING;
+            (self.priority as u64).saturating_add(waited)
+        }
+
+        /// Check that a worker may claim tasks of the job
+        /// Returns an error if the worker lacks the reputation or capabilities the job requires
+        pub fn accepts(&self, worker: &Worker, reputation: u32) -> Result<(), Error> {
+            if reputation < self.min_reputation {
+                return Err(Error::ReputationTooLow);
+            }
+
+            if let Some(requirements) = &self.requirements {
+                if !worker.capabilities.satisfies(requirements) {
+                    return Err(Error::RequirementsNotMet);
+                }
+            }
+
+            Ok(())
+        }
+
+        /// Open job queue of the job, by priority and by the runtime it requires
+        pub fn queue(&self) -> u8 {
+            let slot = match &self.requirements {
+                Some(requirements) => requirements.runtime as usize,
+                None => RUNTIME_QUEUES - 1,
+            };
+            open_queue(self.priority as usize, slot)
+        }
+
+        /// Returns true if the job has tasks that can still be claimed at `now`
+        pub fn is_open(&self, now: Timestamp) -> bool {
+            !self.is_expired(now)
+                && self.pending_parents == 0
+                && self
+                    .tasks
+                    .iter()
+                    .any(|task| task.status == JobStatus::Created)
+        }
+
+        /// Scan active tasks for account if, returns True if account id is found
+        pub fn has_worker(&self, worker: AccountId, task_status: Option<JobStatus>) -> bool {
+            let task_status = task_status.unwrap_or(JobStatus::InProgress);
+
+            for task in self.tasks.iter().filter(|task| task.status == task_status) {
+                if let Some(worker_id) = task.worker {
+                    if worker_id == worker {
+                        return true;
+                    }
+                }
+            }
+
+            false
+        }
+    }
+
+    /// A job as written to storage, tagged with the layout it was written in
+    /// Older variants stay decodable after an upgrade and are converted on load
+    #[cfg_attr(
+        feature = "std",
+        derive(Debug, PartialEq, ink::storage::traits::StorageLayout)
+    )]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    pub enum JobRecord {
+        V1(JobV1),
+        V2(Job),
+    }
+
+    impl JobRecord {
+        /// Convert the record to the current job layout
+        pub fn into_job(self) -> Job {
+            match self {
+                JobRecord::V1(job) => job.into(),
+                JobRecord::V2(job) => job,
+            }
+        }
+
+        /// Returns true if the record is written in the current layout
+        pub fn is_current(&self) -> bool {
+            matches!(self, JobRecord::V2(_))
+        }
+    }
+
+    /// Layout of a stored task up to storage version 1
+    /// Frozen, it must keep decoding the records written in that layout
+    #[cfg_attr(
+        feature = "std",
+        derive(Debug, PartialEq, ink::storage::traits::StorageLayout)
+    )]
+    #[derive(Clone)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    pub struct TaskV1 {
+        id: u32,
+        worker: Option<AccountId>,
+        status: JobStatus,
+        retries: u32,
+        created_at: Timestamp,
+        updated_at: Option<Timestamp>,
+        completed_at: Option<Timestamp>,
+        reward: Balance,
+        lease_expires_at: Option<Timestamp>,
+        cid_result: Option<String>,
+    }
+
+    /// Layout of a stored job up to storage version 1
+    /// Frozen, it must keep decoding the records written in that layout
+    #[cfg_attr(
+        feature = "std",
+        derive(Debug, PartialEq, ink::storage::traits::StorageLayout)
+    )]
+    #[derive(Clone)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    pub struct JobV1 {
+        id: u32,
+        creator: AccountId,
+        cid_manifest: String,
+        cid_results: Option<Vec<String>>,
+        tip: Option<Balance>,
+        escrow: Balance,
+        tasks: Vec<TaskV1>,
+        created_at: Timestamp,
+        updated_at: Option<Timestamp>,
+        completed_at: Option<Timestamp>,
+        deadline: Option<Timestamp>,
+        min_reputation: u32,
+        priority: Priority,
+        requirements: Option<JobRequirements>,
+    }
+
+    impl From<TaskV1> for Task {
+        /// Version 1 tasks ran once, each as its own shard, and were paid on completion
+        fn from(task: TaskV1) -> Self {
+            Self {
+                id: task.id,
+                worker: task.worker,
+                status: task.status,
+                retries: task.retries,
+                created_at: task.created_at,
+                updated_at: task.updated_at,
+                completed_at: task.completed_at,
+                reward: task.reward,
+                lease_expires_at: task.lease_expires_at,
+                cid_result: task.cid_result,
+                paid: task.status == JobStatus::Completed,
+                ..Task::with_id(task.id)
+            }
+        }
+    }
+
+    impl From<JobV1> for Job {
+        /// Version 1 jobs were batches of single runs without parents
+        fn from(job: JobV1) -> Self {
+            Self {
+                id: job.id,
+                creator: job.creator,
+                cid_manifest: job.cid_manifest,
+                cid_results: job.cid_results,
+                tip: job.tip,
+                escrow: job.escrow,
+                tasks: job.tasks.into_iter().map(Task::from).collect(),
+                created_at: job.created_at,
+                updated_at: job.updated_at,
+                completed_at: job.completed_at,
+                deadline: job.deadline,
+                min_reputation: job.min_reputation,
+                priority: job.priority,
+                requirements: job.requirements,
+                ..Job::default()
+            }
+        }
+    }
+
+    /// Lightweight view of a job, without its tasks
+    #[cfg_attr(feature = "std", derive(Debug, PartialEq))]
+    #[derive(Clone)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    pub struct JobSummary {
+        /// The job ID
+        id: u32,
+
+        /// The account id of the creator of the job
+        creator: AccountId,
+
+        /// The content id of the manifest for the job
+        cid_manifest: String,
+
+        /// The aggregated status of the job tasks
+        status: JobStatus,
+
+        /// The number of tasks within the job
+        task_count: u32,
+
+        /// The tip for the worker(s)
+        tip: Option<Balance>,
+
+        /// The part of the tip still held in escrow
+        escrow: Balance,
+
+        /// The timestamp when the job was created
+        created_at: Timestamp,
+
+        /// The timestamp when the job was completed
+        completed_at: Option<Timestamp>,
+
+        /// The timestamp the job must be completed by
+        deadline: Option<Timestamp>,
+
+        /// The scheduling priority of the job
+        priority: Priority,
+
+        /// The number of parent jobs that have not completed yet
+        pending_parents: u32,
+
+        /// The shape of the job
+        kind: JobKind,
+    }
+
+    /// A page of job summaries returned by the listing messages
+    #[cfg_attr(feature = "std", derive(Debug, PartialEq))]
+    #[derive(Clone)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    pub struct JobPage {
+        /// The jobs of the page
+        jobs: Vec<JobSummary>,
+
+        /// The offset to pass to fetch the next page
+        /// None once the listing is exhausted
+        next: Option<u32>,
+    }
+
+    #[cfg_attr(
+        feature = "std",
+        derive(Debug, PartialEq, ink::storage::traits::StorageLayout)
+    )]
+    #[derive(Clone)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    pub struct Worker {
+        /// The amount staked by the worker
+        stake: Balance,
+
+        /// The timestamp when the worker registered
+        registered_at: Timestamp,
+
+        /// The timestamp before which the worker may not unregister
+        /// The cooldown is restarted every time the worker claims a task
+        cooldown_until: Timestamp,
+
+        /// The resources and placement the worker offers
+        capabilities: Capabilities,
+    }
+
+    #[cfg_attr(
+        feature = "std",
+        derive(Debug, PartialEq, ink::storage::traits::StorageLayout)
+    )]
+    #[derive(Clone, Default)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    pub struct WorkerStats {
+        /// The number of tasks completed by the worker
+        completed: u32,
+
+        /// The number of tasks the worker reported as failed
+        failed: u32,
+
+        /// The number of tasks reclaimed from the worker after its lease expired
+        expired: u32,
+
+        /// The number of results rejected for disagreeing with the other replicas
+        rejected: u32,
+
+        /// The total amount paid to the worker
+        earnings: Balance,
+    }
+
+    impl WorkerStats {
+        /// Reputation score of the worker
+        /// Completed tasks count towards the score, failed, expired and rejected tasks count against it
+        pub fn reputation(&self) -> u32 {
+            self.completed.saturating_sub(
+                self.failed
+                    .saturating_add(self.expired)
+                    .saturating_add(self.rejected),
+            )
+        }
+    }
+
+    /// Defines the storage of your contract.
+    /// Add new fields to the below struct in order
+    /// to add new static storage fields to your contract.
+    /// Plain fields are decoded together, so fields added once the contract is deployed
+    /// must be a `Mapping` or `Lazy` to stay readable after `upgrade`.
+    #[ink(storage)]
+    pub struct NocturneJob {
+        /// The owner of the contract, allowed to change its settings
+        /// None once ownership is renounced
+        owner: Option<AccountId>,
+
+        /// The account allowed to pause and unpause the contract besides the owner
+        guardian: Option<AccountId>,
+
+        /// While paused no new jobs are submitted or claimed
+        /// Completions, failures, cancellations and refunds keep working
+        paused: bool,
+
+        /// Store listing of all active jobs, keyed by job id
+        jobs: Mapping<u32, JobRecord>,
+
+        /// Version of the storage layout, below `STORAGE_VERSION` until `migrate` has finished
+        storage_version: u32,
+
+        /// Id of the next job `migrate` will rewrite
+        migrated_jobs: u32,
+
+        /// The id assigned to the next submitted job
+        /// Job ids are never reused
+        next_job_id: u32,
+
+        /// Max retries for a task
+        max_retries: u32,
+
+        /// Max tasks a job may have
+        max_tasks: u32,
+
+        /// How long a worker may hold a task without a heartbeat
+        lease_duration: Timestamp,
+
+        /// Registered workers allowed to claim tasks
+        workers: Mapping<AccountId, Worker>,
+
+        /// Minimum stake a worker must lock to claim tasks
+        min_stake: Balance,
+
+        /// How long a worker must wait after its last claim before unregistering
+        worker_cooldown: Timestamp,
+
+        /// Share of the stake slashed for a failed or abandoned task, in basis points
+        slash_bps: u32,
+
+        /// Track record of every worker that has claimed a task
+        stats: Mapping<AccountId, WorkerStats>,
+
+        /// Ids of the jobs submitted by a creator, keyed by creator and position
+        creator_jobs: Mapping<(AccountId, u32), u32>,
+
+        /// Number of jobs submitted by a creator
+        creator_job_count: Mapping<AccountId, u32>,
+
+        /// Ids of the jobs a worker has claimed tasks in, keyed by worker and position
+        worker_jobs: Mapping<(AccountId, u32), u32>,
+
+        /// Number of jobs a worker has claimed tasks in
+        worker_job_count: Mapping<AccountId, u32>,
+
+        /// Position of a job in the worker's job listing, keyed by worker and job id
+        worker_job_index: Mapping<(AccountId, u32), u32>,
+
+        /// Tasks a worker currently holds in progress
+        worker_tasks: Mapping<AccountId, Vec<TaskRef>>,
+
+        /// Queues of jobs with unclaimed tasks, one per priority and required runtime, keyed by queue and position
+        /// Removed entries leave a gap that is skipped once it reaches the front
+        open_jobs: Mapping<(u8, u32), u32>,
+
+        /// Queue and position of an open job, keyed by job id
+        open_job_position: Mapping<u32, (u8, u32)>,
+
+        /// Position of the front of each open job queue
+        open_head: [u32; OPEN_QUEUES],
+
+        /// Position after the back of each open job queue
+        open_tail: [u32; OPEN_QUEUES],
+
+        /// Ids of the jobs waiting for a job to complete, keyed by job id
+        dependents: Mapping<u32, Vec<u32>>,
+
+        /// Roles granted by the owner, keyed by role and account
+        roles: Mapping<(Role, AccountId), ()>,
+
+        /// While permissioned only accounts with the Worker role may claim tasks
+        /// and only operators may reclaim expired tasks
+        permissioned: Lazy<bool>,
+
+        /// Time the creator of a job has to dispute an accepted result before it is paid
+        /// Results are paid right away if unset
+        challenge_period: Lazy<Timestamp>,
+
+        /// Time after the first commitment of a shard before its results may be revealed
+        /// `DEFAULT_REVEAL_WINDOW` if unset
+        reveal_window: Lazy<Timestamp>,
+
+        /// Tasks a worker may hold in progress at once, unless it declared its own capacity
+        /// `DEFAULT_CONCURRENCY` if unset
+        default_concurrency: Lazy<u32>,
+    }
+    //-- End of Storage
+
+    impl NocturneJob {
+        /// Constructor that initializes an empty job listing with the given limits.
+        #[ink(constructor)]
+        pub fn new(max_retries: u32, max_tasks: u32) -> Self {
+            if max_retries == 0 || max_tasks == 0 {
+                panic!("Max retries and max tasks must be greater than 0");
+            }
+
+            Self {
+                owner: Some(Self::env().caller()),
+                guardian: None,
+                paused: false,
+                jobs: Mapping::default(),
+                storage_version: STORAGE_VERSION,
+                migrated_jobs: 0,
+                next_job_id: 0,
+                max_retries,
+                max_tasks,
+                lease_duration: DEFAULT_LEASE_DURATION,
+                workers: Mapping::default(),
+                min_stake: DEFAULT_MIN_STAKE,
+                worker_cooldown: DEFAULT_WORKER_COOLDOWN,
+                slash_bps: DEFAULT_SLASH_BPS,
+                stats: Mapping::default(),
+                creator_jobs: Mapping::default(),
+                creator_job_count: Mapping::default(),
+                worker_jobs: Mapping::default(),
+                worker_job_count: Mapping::default(),
+                worker_job_index: Mapping::default(),
+                worker_tasks: Mapping::default(),
+                open_jobs: Mapping::default(),
+                open_job_position: Mapping::default(),
+                open_head: [0; OPEN_QUEUES],
+                open_tail: [0; OPEN_QUEUES],
+                dependents: Mapping::default(),
+                roles: Mapping::default(),
+                permissioned: Lazy::default(),
+                challenge_period: Lazy::default(),
+                reveal_window: Lazy::default(),
+                default_concurrency: Lazy::default(),
+            }
+        }
+
+        /// The default constructor
+        #[ink(constructor, default)]
+        pub fn default() -> Self {
+            Self::new(3, 15)
+        }
+
+        /// Submit a new job (UI endpoint)
+        /// The job is created and added to the jobs storage under a new unique job id
+        /// The job is created with the creator account id, the data content id and the manifest content id
+        /// The job is created with the status Created
+        /// The job is created with the created_at timestamp
+        /// Submit is a payable function, the caller may attach a deposit to the call. Deposit is a 'tip' for the worker
+        /// Optional settings such as deadline, minimum worker reputation and priority are given in `options`
+        /// With `replicas` set, every task runs on that many distinct workers and is accepted once `quorum` results match
+        /// With `parents` set, the tasks can only be claimed once every parent job has completed
+        /// Parents must be jobs of the same creator
+        /// A map-reduce job runs `task_count` map tasks, then a reduce task over their results
+        /// Returns the id of the new job
+        #[ink(message, payable)]
+        pub fn submit(
+            &mut self,
+            cid_manifest: String,
+            task_count: u32,
+            options: JobOptions,
+        ) -> Result<u32, Error> {
+            self.ensure_not_paused()?;
+
+            let creator = self.env().caller();
+
+            // Each task runs on `replicas` workers and needs a majority of them to agree
+            let replicas = options.replicas.max(1);
+            let quorum = if options.quorum == 0 {
+                replicas
+            } else {
+                options.quorum
+            };
+            if quorum > replicas || quorum.saturating_mul(2) <= replicas {
+                return Err(Error::SubmitFailed);
+            }
+
+            // The reduce tasks of a map-reduce job count towards the limit
+            let total_tasks = task_count.saturating_mul(replicas);
+            let reduce_tasks = match options.kind {
+                JobKind::Batch => 0,
+                JobKind::MapReduce => replicas,
+            };
+            if task_count == 0 || total_tasks.saturating_add(reduce_tasks) > self.max_tasks {
+                return Err(Error::SubmitFailed);
+            }
+
+            // Deadline must be in the future
+            if matches!(options.deadline, Some(deadline) if deadline <= self.env().block_timestamp())
+            {
+                return Err(Error::SubmitFailed);
+            }
+
+            // Tip is None if 0 value is transferred else the value transferred
+            let transfered_value = self.env().transferred_value();
+            let tip = if transfered_value == 0 {
+                None
+            } else {
+                Some(transfered_value)
+            };
+
+            // Parents must exist, belong to the creator and not have failed, the job waits for those not completed yet
+            let mut parents = options.parents;
+            parents.sort_unstable();
+            parents.dedup();
+            if parents.len() > MAX_PARENTS {
+                return Err(Error::SubmitFailed);
+            }
+
+            // A job releases or fails its dependents in a single call, so their number is capped
+            let mut pending_parents = Vec::new();
+            for &parent_id in parents.iter() {
+                let parent = self.load_job(parent_id).ok_or(Error::SubmitFailed)?;
+                if parent.creator != creator {
+                    return Err(Error::NotOwner);
+                }
+                if !parent.can_complete() {
+                    return Err(Error::SubmitFailed);
+                }
+                if parent.get_job_status() != JobStatus::Completed {
+                    let dependents = self.dependents.get(parent_id).unwrap_or_default();
+                    if dependents.len() >= MAX_DEPENDENTS {
+                        return Err(Error::SubmitFailed);
+                    }
+                    pending_parents.push(parent_id);
+                }
+            }
+
+            let tasks = (0..total_tasks)
+                .map(|id| Task {
+                    shard: id / replicas,
+                    ..Task::with_id(id)
+                })
+                .collect();
+
+            let job_id = self.next_job_id;
+            self.next_job_id = job_id.checked_add(1).ok_or(Error::SubmitFailed)?;
+
+            let mut job = Job {
+                id: job_id,
+                creator: self.env().caller(),
+                cid_manifest,
+                cid_results: None,
+                tip,
+                escrow: 0,
+                tasks,
+                created_at: self.env().block_timestamp(),
+                updated_at: None,
+                completed_at: None,
+                deadline: options.deadline,
+                min_reputation: options.min_reputation,
+                priority: options.priority,
+                requirements: options.requirements,
+                replicas,
+                quorum,
+                parents,
+                pending_parents: pending_parents.len() as u32,
+                kind: options.kind,
+                reduce_reward: 0,
+                reduce_shard: None,
+                cancelled: false,
+            };
+            job.split_tip();
+
+            if self.jobs.contains(job_id) {
+                return Err(Error::SubmitFailed);
+            }
+            self.save_job(&job);
+
+            let position = self.creator_job_count.get(creator).unwrap_or(0);
+            self.creator_jobs.insert((creator, position), &job_id);
+            self.creator_job_count
+                .insert(creator, &position.saturating_add(1));
+            self.sync_open_job(&job);
+
+            for parent_id in pending_parents {
+                let mut dependents = self.dependents.get(parent_id).unwrap_or_default();
+                dependents.push(job_id);
+                self.dependents.insert(parent_id, &dependents);
+            }
+
+            self.env().emit_event(JobSubmitted { creator, job_id });
+
+            Ok(job_id)
+        }
+
+        /// Claim a job (Backend endpoint)
+        /// The job is updated with the status Pending
+        /// The job is updated with the updated_at timestamp
+        /// The job is updated with the worker account id
+        #[ink(message)]
+        pub fn claim(&mut self, job_id: u32) -> Result<Job, Error> {
+            self.ensure_not_paused()?;
+
+            self.assign_task(job_id, None).map(|(job, _)| job)
+        }
+
+        /// Claim a specific task of a job (Backend endpoint)
+        /// Lets a worker pick the shard it already holds data for
+        /// Returns an error if the task is taken, has failed or completed
+        #[ink(message)]
+        pub fn claim_task(&mut self, job_id: u32, task_id: u32) -> Result<Job, Error> {
+            self.ensure_not_paused()?;
+
+            self.assign_task(job_id, Some(task_id)).map(|(job, _)| job)
+        }
+
+        /// Claim first available job (Backend endpoint)
+        /// Pick the highest priority job the caller may claim from the open job queues
+        /// Jobs gain priority while they wait at the front of their queue, ties go to the oldest job
+        /// Calls claim with the job id
+        /// Returns an error if no job is available
+        #[ink(message)]
+        pub fn claim_first(&mut self) -> Result<Job, Error> {
+            self.ensure_not_paused()?;
+
+            // Only registered workers with enough stake may claim tasks
+            let worker = self.check_worker(self.env().caller())?;
+
+            // If caller already holds as many tasks as it may run at once, return error
+            if self.is_busy(self.env().caller(), &worker) {
+                return Err(Error::CallerBusy);
+            }
+
+            match self.next_open_job(&worker) {
+                Some(job_id) => self.claim(job_id),
+                None => Err(Error::NoJobs),
+            }
+        }
+
+        /// Claim several tasks at once (Backend endpoint)
+        /// Tasks are picked one after the other as by claim_first
+        /// Claims up to `count` tasks, and no more than the caller may still run at once
+        /// Returns the (job id, task id) pairs claimed, or an error if no job is available
+        #[ink(message)]
+        pub fn claim_batch(&mut self, count: u32) -> Result<Vec<TaskRef>, Error> {
+            self.ensure_not_paused()?;
+
+            let caller = self.env().caller();
+
+            // Only registered workers with enough stake may claim tasks
+            let worker = self.check_worker(caller)?;
+
+            // If caller already holds as many tasks as it may run at once, return error
+            if self.is_busy(caller, &worker) {
+                return Err(Error::CallerBusy);
+            }
+
+            let held = self.worker_tasks.get(caller).unwrap_or_default().len() as u32;
+            let count = count.min(self.concurrency(&worker).saturating_sub(held));
+
+            let mut claimed = Vec::new();
+            while (claimed.len() as u32) < count {
+                let Some(job_id) = self.next_open_job(&worker) else {
+                    break;
+                };
+                let (_, task_id) = self.assign_task(job_id, None)?;
+                claimed.push((job_id, task_id));
+            }
+
+            if claimed.is_empty() {
+                return Err(Error::NoJobs);
+            }
+
+            Ok(claimed)
+        }
+
+        /// Assign the caller a task of a job, the given one or else the first it may claim
+        /// Returns the updated job and the id of the claimed task
+        fn assign_task(&mut self, job_id: u32, task_id: Option<u32>) -> Result<(Job, u32), Error> {
+            let caller = self.env().caller();
+
+            // Only registered workers with enough stake may claim tasks
+            let mut worker = self.check_worker(caller)?;
+
+            // If caller already holds as many tasks as it may run at once, return error
+            if self.is_busy(caller, &worker) {
+                return Err(Error::CallerBusy);
+            }
+
+            // If job has available tasks, claim the first available task
+            let mut job = self.load_job(job_id).ok_or(Error::ClaimFailed)?;
+
+            // If caller lacks the reputation or capabilities the job requires, return error
+            job.accepts(&worker, self.get_worker_stats(caller).reputation())?;
+
+            let task = match task_id {
+                Some(task_id) => job.claim_task_at(task_id, caller, self.lease_duration)?,
+                None => job.claim_task(caller, self.lease_duration)?,
+            };
+
+            job.updated_at = self.env().block_timestamp().into();
+
+            // Update job with the updated task
+            self.save_job(&job);
+            self.sync_open_job(&job);
+
+            // Index the claimed task for the worker
+            self.track_task(caller, job_id, task.id);
+
+            // Restart the worker cooldown
+            worker.cooldown_until = self
+                .env()
+                .block_timestamp()
+                .saturating_add(self.worker_cooldown);
+            self.workers.insert(caller, &worker);
+
+            self.env().emit_event(TaskClaimed {
+                worker: caller,
+                job_id,
+                task_id: task.id,
+            });
+
+            Ok((job, task.id))
+        }
+
+        /// Pick the highest priority job the caller may claim from the open job queues
+        /// Only the first claimable job of each queue competes, aged by how long it has waited,
+        /// ties go to the oldest job
+        fn next_open_job(&mut self, worker: &Worker) -> Option<u32> {
+            // Skip jobs asking for more reputation or capabilities than the caller has
+            let reputation = self.get_worker_stats(self.env().caller()).reputation();
+            let now = self.env().block_timestamp();
+
+            // Only look at the queues of jobs without requirements and of runtimes the caller runs,
+            // so jobs for other runtimes never crowd out the ones it can take
+            let runs = |slot: usize| {
+                slot == RUNTIME_QUEUES - 1
+                    || worker
+                        .capabilities
+                        .runtimes
+                        .iter()
+                        .any(|&runtime| runtime as usize == slot)
+            };
+
+            // Queues are in the order jobs became claimable, a job put back after a failed or reclaimed task
+            // waits behind the jobs queued before it, so each queue offers its first claimable job
+            let mut best: Option<(u64, Timestamp, u32)> = None;
+            for level in 0..PRIORITY_LEVELS {
+                for slot in (0..RUNTIME_QUEUES).filter(|&slot| runs(slot)) {
+                    let queue = open_queue(level, slot);
+                    let Some(job) = self.first_open_job(queue, worker, reputation, now) else {
+                        continue;
+                    };
+
+                    let candidate = (job.effective_priority(now), job.created_at, job.id);
+                    let better = best.is_none_or(|(priority, created_at, _)| {
+                        candidate.0 > priority
+                            || (candidate.0 == priority && candidate.1 < created_at)
+                    });
+                    if better {
+                        best = Some(candidate);
+                    }
+                }
+            }
+
+            best.map(|(_, _, job_id)| job_id)
+        }
+
+        /// Fail a job (Backend endpoint)
+        /// The job is updated with the status Failed
+        /// The job is updated with the updated_at timestamp
+        /// The worker account id is removed from the job workers
+        /// Part of the worker stake is slashed and paid to the creator of the job
+        #[ink(message)]
+        pub fn fail(&mut self, job_id: u32, task_id: u32) -> Result<(), Error> {
+            let caller = self.env().caller();
+            let mut job = self.load_job(job_id).ok_or(Error::UpdateFailed)?;
+            let task = job
+                .tasks
+                .get_mut(task_id as usize)
+                .ok_or(Error::UpdateFailed)?;
+
+            // If task not owned by caller, return error
+            if task.worker != Some(caller) {
+                return Err(Error::NotWorker);
+            }
+
+            // If task not in valid in progress state, return error
+            if task.status != JobStatus::InProgress {
+                return Err(Error::UpdateFailed);
+            }
+
+            // Update task
+            let shard = task.shard;
+            let status = task.release(self.max_retries)?;
+
+            // Update job
+            // Once the task is out of retries the whole job fails and the escrow is refunded
+            job.updated_at = self.env().block_timestamp().into();
+            let mut refund = 0;
+            if status == JobStatus::Failed {
+                refund = self.close_job(&mut job);
+            }
+
+            // Update job with the updated task
+            self.save_job(&job);
+            self.sync_open_job(&job);
+            self.untrack_task(caller, job_id, task_id);
+
+            let mut stats = self.get_worker_stats(caller);
+            stats.failed = stats.failed.saturating_add(1);
+            self.stats.insert(caller, &stats);
+
+            self.slash(caller, job_id, task_id, job.creator)?;
+            self.refund(job_id, job.creator, refund)?;
+            self.update_dependents(&job)?;
+
+            // A replica released once the reveals opened can no longer commit, its shard settles without it
+            if status != JobStatus::Failed && job.replicas > 1 {
+                self.settle(job, shard)?;
+            }
+
+            // Emit TaskFailed event
+            self.env().emit_event(TaskFailed {
+                worker: caller,
+                job_id,
+                task_id,
+            });
+
+            Ok(())
+        }
+
+        /// Extend the lease on a task (Backend endpoint)
+        /// The worker holding the task signals it is still working on it
+        /// Returns the new lease expiry timestamp
+        #[ink(message)]
+        pub fn heartbeat(&mut self, job_id: u32, task_id: u32) -> Result<Timestamp, Error> {
+            let caller = self.env().caller();
+            let now = self.env().block_timestamp();
+            let mut job = self.load_job(job_id).ok_or(Error::UpdateFailed)?;
+            let task = job
+                .tasks
+                .get_mut(task_id as usize)
+                .ok_or(Error::UpdateFailed)?;
+
+            // If task not owned by caller, return error
+            if task.worker != Some(caller) {
+                return Err(Error::NotWorker);
+            }
+
+            // If task not in valid in progress state, return error
+            if task.status != JobStatus::InProgress {
+                return Err(Error::UpdateFailed);
+            }
+
+            // A lease that ran out may already be up for grabs
+            if task.is_expired(now) {
+                return Err(Error::LeaseExpired);
+            }
+
+            let lease_expires_at = now.saturating_add(self.lease_duration);
+            task.lease_expires_at = Some(lease_expires_at);
+            task.updated_at = Some(now);
+
+            // Update job with the updated task
+            self.save_job(&job);
+
+            Ok(lease_expires_at)
+        }
+
+        /// Reclaim tasks with expired leases (General purpose endpoint)
+        /// Anyone may call this to release tasks abandoned by their workers
+        /// Only operators may call it while the contract is permissioned
+        /// Expired tasks go back to Created, or to Failed once they are out of retries
+        /// Expired replicas of a shard whose reveals opened are closed instead
+        /// Workers that abandoned a task are slashed in favour of the creator of the job
+        /// Returns the number of reclaimed tasks
+        #[ink(message)]
+        pub fn reclaim_expired(&mut self, job_id: u32) -> Result<u32, Error> {
+            if self.is_permissioned() {
+                self.ensure_role(Role::Operator)?;
+            }
+
+            let now = self.env().block_timestamp();
+            let mut job = self.load_job(job_id).ok_or(Error::NoJobs)?;
+
+            let mut expired: Vec<(u32, AccountId)> = Vec::new();
+            let mut failed = false;
+            for (task_id, task) in job
+                .tasks
+                .iter_mut()
+                .enumerate()
+                .filter(|(_, task)| task.is_expired(now))
+            {
+                if let Some(worker) = task.worker {
+                    expired.push((task_id as u32, worker));
+                }
+                if task.release(self.max_retries)? == JobStatus::Failed {
+                    failed = true;
+                }
+            }
+
+            if expired.is_empty() {
+                return Ok(0);
+            }
+
+            // Update job
+            // A task out of retries fails the whole job and the escrow is refunded
+            job.updated_at = now.into();
+            let mut refund = 0;
+            for (task_id, worker) in expired.iter() {
+                self.untrack_task(*worker, job_id, *task_id);
+            }
+            if failed {
+                refund = self.close_job(&mut job);
+            }
+
+            // Update job with the updated tasks
+            self.save_job(&job);
+            self.sync_open_job(&job);
+
+            for (task_id, worker) in expired.iter() {
+                self.env().emit_event(TaskExpired {
+                    worker: *worker,
+                    job_id,
+                    task_id: *task_id,
+                });
+                let mut stats = self.get_worker_stats(*worker);
+                stats.expired = stats.expired.saturating_add(1);
+                self.stats.insert(*worker, &stats);
+
+                self.slash(*worker, job_id, *task_id, job.creator)?;
+            }
+
+            self.refund(job_id, job.creator, refund)?;
+            self.update_dependents(&job)?;
+
+            // Replicas released once the reveals opened can no longer commit, their shards settle without them
+            if !failed && job.replicas > 1 {
+                for (task_id, _) in expired.iter() {
+                    let shard = job.tasks[*task_id as usize].shard;
+                    job = self.settle(job, shard)?;
+                }
+            }
+
+            Ok(expired.len() as u32)
+        }
+
+        /// Complete a job task (Backend endpoint)
+        /// The job is updated with the status Completed
+        /// The job is updated with the updated_at timestamp
+        /// The job is updated with the result content id
+        /// The worker is paid once the result is accepted by the quorum of its shard
+        /// With a challenge period set, the payout waits until the creator had the chance to dispute the result
+        /// Replicated tasks must use `commit_result` and `reveal_result` instead
+        #[ink(message)]
+        pub fn complete(
+            &mut self,
+            job_id: u32,
+            task_id: u32,
+            cid_result: String,
+        ) -> Result<(), Error> {
+            let caller = self.env().caller();
+            let job = self.load_job(job_id).ok_or(Error::UpdateFailed)?;
+            let task = job.tasks.get(task_id as usize).ok_or(Error::UpdateFailed)?;
+
+            // If task not owned by caller, return error
+            if task.worker != Some(caller) {
+                return Err(Error::NotWorker);
+            }
+
+            // If task not in valid in progress state, return error
+            if task.status != JobStatus::InProgress {
+                return Err(Error::UpdateFailed);
+            }
+
+            // Replicas could copy each other's result if it were sent in the clear
+            if job.replicas > 1 {
+                return Err(Error::CommitRequired);
+            }
+
+            self.finish_task(job, task_id, cid_result)
+        }
+
+        /// Commit to the result of a task (Backend endpoint)
+        /// `commitment` is the `result_commitment` of the result content id, a secret salt and the caller
+        /// The result is disclosed later with `reveal_result`, so other replicas can't copy it
+        #[ink(message)]
+        pub fn commit_result(
+            &mut self,
+            job_id: u32,
+            task_id: u32,
+            commitment: Hash,
+        ) -> Result<(), Error> {
+            let caller = self.env().caller();
+            let now = self.env().block_timestamp();
+            let mut job = self.load_job(job_id).ok_or(Error::UpdateFailed)?;
+            let task = job.tasks.get(task_id as usize).ok_or(Error::UpdateFailed)?;
+
+            // If task not owned by caller, return error
+            if task.worker != Some(caller) {
+                return Err(Error::NotWorker);
+            }
+
+            // If task not in valid in progress state, return error
+            if task.status != JobStatus::InProgress {
+                return Err(Error::UpdateFailed);
+            }
+
+            if task.commitment.is_some() {
+                return Err(Error::AlreadyCommitted);
+            }
+
+            // Results may be public once reveals opened, committing now could copy them
+            if job.reveal_open(task.shard, now, self.get_reveal_window()) {
+                return Err(Error::CommitClosed);
+            }
+
+            let task = &mut job.tasks[task_id as usize];
+            task.commitment = Some(commitment);
+            task.committed_at = Some(now);
+            task.updated_at = Some(now);
+
+            // Update job with the committed task
+            self.save_job(&job);
+
+            self.env().emit_event(ResultCommitted {
+                worker: caller,
+                job_id,
+                task_id,
+            });
+
+            Ok(())
+        }
+
+        /// Reveal the result of a task committed with `commit_result` (Backend endpoint)
+        /// Results are revealed once every replica of the shard has committed, or the reveal window has passed
+        /// Replicas that have not committed by then are closed, the shard settles on the replicas that did
+        /// The result only counts if it matches the commitment, the task is then completed as with `complete`
+        #[ink(message)]
+        pub fn reveal_result(
+            &mut self,
+            job_id: u32,
+            task_id: u32,
+            cid_result: String,
+            salt: Hash,
+        ) -> Result<(), Error> {
+            let caller = self.env().caller();
+            let job = self.load_job(job_id).ok_or(Error::UpdateFailed)?;
+            let task = job.tasks.get(task_id as usize).ok_or(Error::UpdateFailed)?;
+
+            // If task not owned by caller, return error
+            if task.worker != Some(caller) {
+                return Err(Error::NotWorker);
+            }
+
+            // If task not in valid in progress state, return error
+            if task.status != JobStatus::InProgress {
+                return Err(Error::UpdateFailed);
+            }
+
+            let commitment = task.commitment.ok_or(Error::NotCommitted)?;
+            if !job.reveal_open(
+                task.shard,
+                self.env().block_timestamp(),
+                self.get_reveal_window(),
+            ) {
+                return Err(Error::RevealNotOpen);
+            }
+
+            if result_commitment(&cid_result, &salt, &caller) != commitment {
+                return Err(Error::CommitmentMismatch);
+            }
+
+            self.finish_task(job, task_id, cid_result)
+        }
+
+        /// Complete a task of a job with its result
+        /// Settles the shard of the task, paying or rejecting its replicas once the quorum is reached
+        fn finish_task(
+            &mut self,
+            mut job: Job,
+            task_id: u32,
+            cid_result: String,
+        ) -> Result<(), Error> {
+            let caller = self.env().caller();
+            let job_id = job.id;
+            let task = job
+                .tasks
+                .get_mut(task_id as usize)
+                .ok_or(Error::UpdateFailed)?;
+
+            // Update task status and result content id
+            task.status = JobStatus::Completed;
+            task.cid_result = Some(cid_result);
+            task.completed_at = self.env().block_timestamp().into();
+            task.lease_expires_at = None;
+            let shard = task.shard;
+
+            self.untrack_task(caller, job_id, task_id);
+            let job = self.settle(job, shard)?;
+
+            // Emit JobCompleted event
+            self.env().emit_event(JobCompleted {
+                creator: job.creator,
+                job_id,
+                task_id,
+            });
+
+            Ok(())
+        }
+
+        /// Settle a shard of a job after one of its replicas changed, and store the job
+        /// Replicas are paid or rejected once enough of them agree on the result,
+        /// replicas the shard no longer needs are closed
+        /// Rejected and closed shares go back to the creator, a shard without quorum fails the job
+        /// Returns the stored job
+        fn settle(&mut self, mut job: Job, shard: u32) -> Result<Job, Error> {
+            let job_id = job.id;
+            let now = self.env().block_timestamp();
+            let payable_at = match self.get_challenge_period() {
+                0 => None,
+                period => Some(now.saturating_add(period)),
+            };
+            let settlement = job.settle_shard(shard, payable_at, now, self.get_reveal_window())?;
+            let mut refund: Balance = settlement
+                .rejected
+                .iter()
+                .map(|&(_, _, reward)| reward)
+                .chain(settlement.closed.iter().map(|&(_, _, reward)| reward))
+                .sum();
+            let mut reduce_shard = None;
+            if settlement.failed {
+                refund = refund.saturating_add(self.close_job(&mut job));
+            } else if let Some(reduce) = job.start_reduce() {
+                // The map results are in, the reduce tasks can be claimed
+                reduce_shard = Some(reduce);
+            } else if job.get_job_status() == JobStatus::Completed {
+                // Update job completed_at timestamp and results if all tasks are completed
+                job.completed_at = now.into();
+                job.cid_results = job.results();
+            }
+
+            job.updated_at = now.into();
+
+            // Update job with the settled shard
+            self.save_job(&job);
+            self.sync_open_job(&job);
+
+            for (task_id, worker, reward) in settlement.paid {
+                self.pay(job_id, task_id, worker, reward)?;
+            }
+            for (task_id, worker, _) in settlement.rejected {
+                let mut stats = self.get_worker_stats(worker);
+                stats.rejected = stats.rejected.saturating_add(1);
+                self.stats.insert(worker, &stats);
+
+                self.env().emit_event(TaskRejected {
+                    worker,
+                    job_id,
+                    task_id,
+                });
+            }
+            for (task_id, worker, _) in settlement.closed {
+                if let Some(worker) = worker {
+                    self.untrack_task(worker, job_id, task_id);
+                }
+            }
+            self.refund(job_id, job.creator, refund)?;
+            self.update_dependents(&job)?;
+
+            if let Some(shard) = reduce_shard {
+                self.env().emit_event(ReduceCreated { job_id, shard });
+            }
+
+            Ok(job)
+        }
+
+        /// Release the payout of an accepted result (General purpose endpoint)
+        /// Anyone may call this once the challenge period of the result has passed without a dispute
+        /// Returns the amount paid to the worker
+        #[ink(message)]
+        pub fn release_payout(&mut self, job_id: u32, task_id: u32) -> Result<Balance, Error> {
+            let job = self.load_job(job_id).ok_or(Error::UpdateFailed)?;
+            let task = job.tasks.get(task_id as usize).ok_or(Error::UpdateFailed)?;
+            let payable_at = task.payable_at.ok_or(Error::NoPayout)?;
+            let worker = task.worker.ok_or(Error::NoPayout)?;
+
+            // A disputed payout waits for an auditor
+            if task.dispute.is_some() {
+                return Err(Error::Disputed);
+            }
+
+            // The creator may still dispute the result
+            if self.env().block_timestamp() <= payable_at {
+                return Err(Error::ChallengeActive);
+            }
+
+            self.release(job, task_id, worker)
+        }
+
+        /// Dispute the result of a task (UI endpoint)
+        /// Owner of the job can dispute an accepted result during its challenge period
+        /// The payout is frozen until an auditor resolves the dispute
+        #[ink(message)]
+        pub fn dispute(
+            &mut self,
+            job_id: u32,
+            task_id: u32,
+            reason_cid: String,
+        ) -> Result<(), Error> {
+            let caller = self.env().caller();
+            let mut job = self.load_job(job_id).ok_or(Error::NoJobs)?;
+
+            // If caller is not the owner of the job, return error
+            if job.creator != caller {
+                return Err(Error::NotOwner);
+            }
+
+            let task = job
+                .tasks
+                .get_mut(task_id as usize)
+                .ok_or(Error::UpdateFailed)?;
+            let payable_at = task.payable_at.ok_or(Error::NoPayout)?;
+
+            if task.dispute.is_some() {
+                return Err(Error::Disputed);
+            }
+
+            if self.env().block_timestamp() > payable_at {
+                return Err(Error::ChallengeClosed);
+            }
+
+            task.dispute = Some(reason_cid.clone());
+            job.updated_at = self.env().block_timestamp().into();
+
+            // Update job with the disputed task
+            self.save_job(&job);
+
+            self.env().emit_event(TaskDisputed {
+                creator: caller,
+                job_id,
+                task_id,
+                reason_cid,
+            });
+
+            Ok(())
+        }
+
+        /// Resolve a dispute over the result of a task (Admin endpoint)
+        /// Either releases the reward to the worker, or rejects the result and reopens the task
+        /// A rejected result returns the reward to the job escrow and slashes the worker in favour of the creator
+        /// Only auditors may resolve disputes
+        #[ink(message)]
+        pub fn resolve_dispute(
+            &mut self,
+            job_id: u32,
+            task_id: u32,
+            release: bool,
+        ) -> Result<(), Error> {
+            self.ensure_role(Role::Auditor)?;
+
+            let mut job = self.load_job(job_id).ok_or(Error::NoJobs)?;
+            let task = job
+                .tasks
+                .get_mut(task_id as usize)
+                .ok_or(Error::UpdateFailed)?;
+
+            if task.dispute.is_none() {
+                return Err(Error::NotDisputed);
+            }
+            let worker = task.worker.ok_or(Error::UpdateFailed)?;
+
+            job.updated_at = self.env().block_timestamp().into();
+            if release {
+                task.dispute = None;
+                self.release(job, task_id, worker)?;
+            } else {
+                // The job fails for good if it was already closed
+                task.reopen();
+                let mut refund = 0;
+                if job.get_job_status() == JobStatus::Failed {
+                    refund = self.close_job(&mut job);
+                }
+
+                // Update job with the reopened task
+                self.save_job(&job);
+                self.sync_open_job(&job);
+
+                let mut stats = self.get_worker_stats(worker);
+                stats.rejected = stats.rejected.saturating_add(1);
+                self.stats.insert(worker, &stats);
+
+                self.slash(worker, job_id, task_id, job.creator)?;
+                self.refund(job_id, job.creator, refund)?;
+                self.update_dependents(&job)?;
+            }
+
+            self.env().emit_event(DisputeResolved {
+                job_id,
+                task_id,
+                released: release,
+                resolved_by: self.env().caller(),
+            });
+
+            Ok(())
+        }
+
+        /// Pay out the accepted result of a task and store the job
+        /// The job completes and releases its dependents once its last payout is released,
+        /// a map-reduce job starts its reduce once the last payout of its map results is released
+        /// Returns the amount paid to the worker
+        fn release(
+            &mut self,
+            mut job: Job,
+            task_id: u32,
+            worker: AccountId,
+        ) -> Result<Balance, Error> {
+            let job_id = job.id;
+            let now = self.env().block_timestamp();
+            let reward = job.pay_task(task_id)?;
+            // The map results can no longer be disputed, the reduce tasks can be claimed
+            let reduce_shard = job.start_reduce();
+            let completed = job.get_job_status() == JobStatus::Completed;
+            if completed {
+                // Update job completed_at timestamp and results now that no result can be disputed
+                job.completed_at = now.into();
+                job.cid_results = job.results();
+            }
+
+            job.updated_at = now.into();
+
+            // Update job with the paid task
+            self.save_job(&job);
+            self.sync_open_job(&job);
+
+            self.pay(job_id, task_id, worker, reward)?;
+            if completed {
+                self.update_dependents(&job)?;
+            }
+
+            if let Some(shard) = reduce_shard {
+                self.env().emit_event(ReduceCreated { job_id, shard });
+            }
+
+            Ok(reward)
+        }
+
+        /// Cancel entire job (UI endpoint)
+        /// Owner of the job can cancel the job
+        /// A job whose tasks all completed has nothing left to cancel, unless it still has to start its reduce
+        #[ink(message)]
+        pub fn cancel(&mut self, job_id: u32) -> Result<(), Error> {
+            let caller = self.env().caller();
+            let mut job = self.load_job(job_id).ok_or(Error::NoJobs)?;
+
+            // If caller is not the owner of the job, return error
+            if job.creator != caller {
+                return Err(Error::NotOwner);
+            }
+
+            // A job with every task completed has nothing left to cancel, unless its reduce is still to start
+            // Results in their challenge period are owed to their workers, the creator may dispute them instead
+            let reduce_pending =
+                job.kind == JobKind::MapReduce && job.reduce_shard.is_none() && !job.cancelled;
+            if !reduce_pending
+                && job
+                    .tasks
+                    .iter()
+                    .all(|task| task.status == JobStatus::Completed)
+            {
+                if job.tasks.iter().any(|task| task.payable_at.is_some()) {
+                    return Err(Error::ChallengeActive);
+                }
+                return Err(Error::UpdateFailed);
+            }
+
+            // Set all unfinished tasks to failed
+            // Update job with the status Failed, a map-reduce job no longer starts its reduce
+            job.updated_at = self.env().block_timestamp().into();
+            job.cancelled = true;
+            let refund = self.close_job(&mut job);
+
+            // Update job with the updated task
+            self.save_job(&job);
+            self.sync_open_job(&job);
+
+            // Return the share of the tip for unfinished tasks
+            self.refund(job_id, caller, refund)?;
+            self.update_dependents(&job)?;
+
+            Ok(())
+        }
+
+        /// Withdraw the escrow of an expired job (UI endpoint)
+        /// Owner of the job can take back the share of the tip for tasks that were never completed
+        /// Tasks still in progress must be completed or reclaimed first
+        /// Jobs waiting for an expired job with unclaimed tasks fail along with it
+        /// Returns the amount withdrawn
+        #[ink(message)]
+        pub fn withdraw_expired(&mut self, job_id: u32) -> Result<Balance, Error> {
+            let caller = self.env().caller();
+            let mut job = self.load_job(job_id).ok_or(Error::NoJobs)?;
+
+            // If caller is not the owner of the job, return error
+            if job.creator != caller {
+                return Err(Error::NotOwner);
+            }
+
+            // If job is not expired, return error
+            if job.get_job_status() != JobStatus::Expired {
+                return Err(Error::UpdateFailed);
+            }
+
+            // Workers still holding a task may yet complete it
+            if job
+                .tasks
+                .iter()
+                .any(|task| task.status == JobStatus::InProgress)
+            {
+                return Err(Error::TasksInProgress);
+            }
+
+            job.updated_at = self.env().block_timestamp().into();
+            let refund = job.take_refund();
+
+            // Update job with the emptied escrow
+            self.save_job(&job);
+
+            self.refund(job_id, caller, refund)?;
+            self.update_dependents(&job)?;
+
+            Ok(refund)
+        }
+
+        /// Pass the outcome of a job on to the jobs depending on it (General purpose endpoint)
+        /// Anyone may call this, e.g. to fail the dependents of a job that expired
+        /// or to carry on a cascade of failures that reached `MAX_CASCADE`
+        #[ink(message)]
+        pub fn notify_dependents(&mut self, job_id: u32) -> Result<(), Error> {
+            let job = self.load_job(job_id).ok_or(Error::NoJobs)?;
+
+            self.update_dependents(&job)
+        }
+
+        /// Fetch Job by ID (General purpose endpoint)
+        /// Returns the job with the job id
+        /// Returns None if the job id is not found
+        #[ink(message)]
+        pub fn get_job(&self, job_id: u32) -> Result<Option<Job>, Error> {
+            Ok(self.load_job(job_id))
+        }
+
+        /// Fetch a page of Jobs (General purpose endpoint)
+        /// Returns summaries of up to `limit` jobs starting at job id `offset`
+        /// Use `get_job` to fetch the tasks of a job
+        #[ink(message)]
+        pub fn get_jobs(&self, offset: u32, limit: u32) -> JobPage {
+            self.page_jobs(self.next_job_id, offset, limit, Some, |_| true)
+        }
+
+        /// Fetch a page of Jobs by Creator (General purpose endpoint)
+        /// Returns summaries of the jobs created by the caller, in submission order
+        /// Filter by job status if provided
+        /// Scans up to `limit` of the caller's jobs starting at `offset`, so a page may hold fewer jobs
+        #[ink(message)]
+        pub fn get_jobs_by_creator(
+            &self,
+            job_status: Option<JobStatus>,
+            offset: u32,
+            limit: u32,
+        ) -> JobPage {
+            let caller = self.env().caller();
+            let count = self.creator_job_count.get(caller).unwrap_or(0);
+
+            self.page_jobs(
+                count,
+                offset,
+                limit,
+                |position| self.creator_jobs.get((caller, position)),
+                |job| job_status.is_none_or(|status| job.get_job_status() == status),
+            )
+        }
+
+        /// Fetch a page of Jobs by Worker (General purpose endpoint)
+        /// Returns summaries of the jobs with tasks claimed by the caller, in claim order
+        /// Filter by task status if provided, defaults to tasks in progress
+        /// Scans up to `limit` of the caller's jobs starting at `offset`, so a page may hold fewer jobs
+        #[ink(message)]
+        pub fn get_jobs_by_worker(
+            &self,
+            job_status: Option<JobStatus>,
+            offset: u32,
+            limit: u32,
+        ) -> JobPage {
+            let caller = self.env().caller();
+            let count = self.worker_job_count.get(caller).unwrap_or(0);
+
+            self.page_jobs(
+                count,
+                offset,
+                limit,
+                |position| self.worker_jobs.get((caller, position)),
+                |job| job.has_worker(caller, job_status),
+            )
+        }
+
+        /// Register as a worker (Backend endpoint)
+        /// Register is a payable function, the deposit is locked as the worker stake
+        /// The worker declares its capabilities, it is only handed tasks whose requirements they satisfy
+        /// Registered workers may call again to top up their stake or update their capabilities
+        /// The total stake must reach the minimum stake
+        #[ink(message, payable)]
+        pub fn register_worker(&mut self, capabilities: Capabilities) -> Result<(), Error> {
+            let caller = self.env().caller();
+            let now = self.env().block_timestamp();
+            let deposit = self.env().transferred_value();
+
+            let mut worker = self.workers.get(caller).unwrap_or(Worker {
+                stake: 0,
+                registered_at: now,
+                cooldown_until: now,
+                capabilities: Capabilities::default(),
+            });
+            worker.capabilities = capabilities;
+            worker.stake = worker
+                .stake
+                .checked_add(deposit)
+                .ok_or(Error::InsufficientStake)?;
+            worker.cooldown_until = now.saturating_add(self.worker_cooldown);
+
+            if worker.stake < self.min_stake {
+                return Err(Error::InsufficientStake);
+            }
+
+            self.workers.insert(caller, &worker);
+
+            self.env().emit_event(WorkerRegistered {
+                worker: caller,
+                stake: worker.stake,
+            });
+
+            Ok(())
+        }
+
+        /// Unregister as a worker (Backend endpoint)
+        /// The worker must not hold any task and its cooldown must have passed
+        /// The stake is returned to the worker
+        #[ink(message)]
+        pub fn unregister_worker(&mut self) -> Result<Balance, Error> {
+            let caller = self.env().caller();
+            let worker = self.workers.get(caller).ok_or(Error::NotRegistered)?;
+
+            if self.env().block_timestamp() < worker.cooldown_until {
+                return Err(Error::CooldownActive);
+            }
+
+            // If caller is still working a task in any job, return error
+            if self.has_tasks(caller) {
+                return Err(Error::CallerBusy);
+            }
+
+            self.workers.remove(caller);
+            self.transfer(caller, worker.stake)?;
+
+            self.env().emit_event(WorkerUnregistered {
+                worker: caller,
+                stake: worker.stake,
+            });
+
+            Ok(worker.stake)
+        }
+
+        /// Fetch Worker by account id (General purpose endpoint)
+        /// Returns None if the account is not a registered worker
+        #[ink(message)]
+        pub fn get_worker(&self, worker: AccountId) -> Option<Worker> {
+            self.workers.get(worker)
+        }
+
+        /// Fetch Worker statistics by account id (General purpose endpoint)
+        /// Returns empty statistics if the account never worked a task
+        #[ink(message)]
+        pub fn get_worker_stats(&self, worker: AccountId) -> WorkerStats {
+            self.stats.get(worker).unwrap_or_default()
+        }
+
+        /// Scan up to `limit` entries of a job listing of length `count`, starting at `offset`
+        /// `job_at` maps a position in the listing to a job id
+        /// Returns the summaries of the jobs matching `filter` and the offset of the next page
+        fn page_jobs(
+            &self,
+            count: u32,
+            offset: u32,
+            limit: u32,
+            job_at: impl Fn(u32) -> Option<u32>,
+            filter: impl Fn(&Job) -> bool,
+        ) -> JobPage {
+            let end = offset
+                .saturating_add(limit.clamp(1, MAX_PAGE_SIZE))
+                .min(count);
+
+            let jobs = (offset..end)
+                .filter_map(&job_at)
+                .filter_map(|job_id| self.load_job(job_id))
+                .filter(|job| filter(job))
+                .map(|job| job.summary())
+                .collect();
+            let next = if end < count { Some(end) } else { None };
+
+            JobPage { jobs, next }
+        }
+
+        /// Returns true if the worker holds a task in progress in any job
+        fn has_tasks(&self, account: AccountId) -> bool {
+            self.worker_tasks.contains(account)
+        }
+
+        /// Returns true if the worker holds as many tasks in progress as it may run at once
+        fn is_busy(&self, account: AccountId, worker: &Worker) -> bool {
+            self.worker_tasks.get(account).unwrap_or_default().len() as u32
+                >= self.concurrency(worker)
+        }
+
+        /// Number of tasks the worker may hold in progress at once
+        fn concurrency(&self, worker: &Worker) -> u32 {
+            match worker.capabilities.concurrency {
+                0 => self.get_default_concurrency(),
+                concurrency => concurrency,
+            }
+        }
+
+        /// Index a task claimed by a worker
+        /// The job is added to the worker's job listing the first time the worker claims one of its tasks
+        fn track_task(&mut self, worker: AccountId, job_id: u32, task_id: u32) {
+            let mut tasks = self.worker_tasks.get(worker).unwrap_or_default();
+            tasks.push((job_id, task_id));
+            self.worker_tasks.insert(worker, &tasks);
+
+            if !self.worker_job_index.contains((worker, job_id)) {
+                let position = self.worker_job_count.get(worker).unwrap_or(0);
+                self.worker_jobs.insert((worker, position), &job_id);
+                self.worker_job_index.insert((worker, job_id), &position);
+                self.worker_job_count
+                    .insert(worker, &position.saturating_add(1));
+            }
+        }
+
+        /// Drop a task the worker no longer holds in progress from its index
+        fn untrack_task(&mut self, worker: AccountId, job_id: u32, task_id: u32) {
+            let mut tasks = self.worker_tasks.get(worker).unwrap_or_default();
+            tasks.retain(|&entry| entry != (job_id, task_id));
+
+            if tasks.is_empty() {
+                self.worker_tasks.remove(worker);
+            } else {
+                self.worker_tasks.insert(worker, &tasks);
+            }
+        }
 
-            // Aggregate task status to determine job status
-            // If any task is failed, job is failed
-            // If any task is in progress and none failed, job is in progress
-            // If all tasks are completed, job is completed
-            // If all tasks are created, job is created
-            let mut has_in_progress = false;
-            let mut has_created = false;
+        /// Fetch the owner of the contract (General purpose endpoint)
+        /// Returns None if ownership was renounced
+        #[ink(message)]
+        pub fn get_owner(&self) -> Option<AccountId> {
+            self.owner
+        }
 
-            for task in self.tasks.iter() {
-                if task.status == JobStatus::Failed {
-                    return JobStatus::Failed;
-                } else if task.status == JobStatus::InProgress {
-                    has_in_progress = true;
-                } else if task.status == JobStatus::Created {
-                    has_created = true;
+        /// Set the max retries for a task (Admin endpoint)
+        /// Only the owner or an operator may change it
+        #[ink(message)]
+        pub fn set_max_retries(&mut self, max_retries: u32) -> Result<(), Error> {
+            self.ensure_role(Role::Operator)?;
+
+            if max_retries == 0 {
+                return Err(Error::InvalidConfig);
+            }
+
+            self.max_retries = max_retries;
+            self.config_changed(Setting::MaxRetries(max_retries));
+
+            Ok(())
+        }
+
+        /// Set the max tasks a job may have (Admin endpoint)
+        /// Only the owner or an operator may change it
+        #[ink(message)]
+        pub fn set_max_tasks(&mut self, max_tasks: u32) -> Result<(), Error> {
+            self.ensure_role(Role::Operator)?;
+
+            if max_tasks == 0 {
+                return Err(Error::InvalidConfig);
+            }
+
+            self.max_tasks = max_tasks;
+            self.config_changed(Setting::MaxTasks(max_tasks));
+
+            Ok(())
+        }
+
+        /// Transfer ownership of the contract (Admin endpoint)
+        /// Only the owner of the contract may transfer it
+        #[ink(message)]
+        pub fn transfer_ownership(&mut self, new_owner: AccountId) -> Result<(), Error> {
+            self.ensure_owner()?;
+
+            self.owner = Some(new_owner);
+            self.config_changed(Setting::Owner(self.owner));
+
+            Ok(())
+        }
+
+        /// Renounce ownership of the contract (Admin endpoint)
+        /// Settings can no longer be changed afterwards
+        #[ink(message)]
+        pub fn renounce_ownership(&mut self) -> Result<(), Error> {
+            self.ensure_owner()?;
+
+            self.owner = None;
+            self.config_changed(Setting::Owner(None));
+
+            Ok(())
+        }
+
+        /// Set the guardian of the contract (Admin endpoint)
+        /// The guardian may pause and unpause the contract
+        /// Only the owner of the contract may change it
+        #[ink(message)]
+        pub fn set_guardian(&mut self, guardian: Option<AccountId>) -> Result<(), Error> {
+            self.ensure_owner()?;
+
+            self.guardian = guardian;
+            self.config_changed(Setting::Guardian(guardian));
+
+            Ok(())
+        }
+
+        /// Pause the contract (Admin endpoint)
+        /// Submitting and claiming work is rejected until the contract is unpaused
+        /// Only the owner, the guardian or an operator may pause it
+        #[ink(message)]
+        pub fn pause(&mut self) -> Result<(), Error> {
+            self.ensure_guardian()?;
+
+            self.paused = true;
+            self.env().emit_event(ContractPaused {
+                by: self.env().caller(),
+            });
+
+            Ok(())
+        }
+
+        /// Unpause the contract (Admin endpoint)
+        /// Only the owner, the guardian or an operator may unpause it
+        #[ink(message)]
+        pub fn unpause(&mut self) -> Result<(), Error> {
+            self.ensure_guardian()?;
+
+            self.paused = false;
+            self.env().emit_event(ContractUnpaused {
+                by: self.env().caller(),
+            });
+
+            Ok(())
+        }
+
+        /// Returns true if the contract is paused (General purpose endpoint)
+        #[ink(message)]
+        pub fn is_paused(&self) -> bool {
+            self.paused
+        }
+
+        /// Replace the code of the contract (Admin endpoint)
+        /// Storage is kept, call `migrate` afterwards if the new code bumps the storage version
+        /// Only the owner of the contract may upgrade it
+        #[ink(message)]
+        pub fn upgrade(&mut self, code_hash: Hash) -> Result<(), Error> {
+            self.ensure_owner()?;
+
+            self.env()
+                .set_code_hash(&code_hash)
+                .map_err(|_| Error::UpgradeFailed)?;
+            self.env().emit_event(CodeUpgraded { code_hash });
+
+            Ok(())
+        }
+
+        /// Rewrite stored jobs to the current layout (Admin endpoint)
+        /// Rewrites up to `limit` jobs per call, continuing where the previous call stopped
+        /// Jobs are readable in any layout meanwhile, so the contract keeps working during the migration
+        /// Returns true once every job has been migrated
+        #[ink(message)]
+        pub fn migrate(&mut self, limit: u32) -> Result<bool, Error> {
+            self.ensure_owner()?;
+
+            if self.storage_version == STORAGE_VERSION {
+                return Ok(true);
+            }
+
+            let end = self
+                .migrated_jobs
+                .saturating_add(limit.clamp(1, MAX_PAGE_SIZE))
+                .min(self.next_job_id);
+            for job_id in self.migrated_jobs..end {
+                if let Some(record) = self.jobs.get(job_id).filter(|record| !record.is_current()) {
+                    self.save_job(&record.into_job());
                 }
             }
+            self.migrated_jobs = end;
 
-            if has_in_progress {
-                JobStatus::InProgress
-            } else if has_created {
-                JobStatus::Created
+            if end < self.next_job_id {
+                return Ok(false);
+            }
+
+            self.storage_version = STORAGE_VERSION;
+            self.migrated_jobs = 0;
+            self.env().emit_event(StorageMigrated {
+                version: STORAGE_VERSION,
+            });
+
+            Ok(true)
+        }
+
+        /// Fetch the storage version of the contract (General purpose endpoint)
+        #[ink(message)]
+        pub fn get_storage_version(&self) -> u32 {
+            self.storage_version
+        }
+
+        /// Load a job from storage in the current layout
+        fn load_job(&self, job_id: u32) -> Option<Job> {
+            self.jobs.get(job_id).map(JobRecord::into_job)
+        }
+
+        /// Write a job to storage in the current layout
+        fn save_job(&mut self, job: &Job) {
+            self.jobs.insert(job.id, &JobRecord::V2(job.clone()));
+        }
+
+        /// Grant a role to an account (Admin endpoint)
+        /// Only the owner of the contract may grant roles
+        #[ink(message)]
+        pub fn grant_role(&mut self, role: Role, account: AccountId) -> Result<(), Error> {
+            self.ensure_owner()?;
+
+            self.roles.insert((role, account), &());
+            self.env().emit_event(RoleGranted {
+                account,
+                role,
+                granted_by: self.env().caller(),
+            });
+
+            Ok(())
+        }
+
+        /// Revoke a role from an account (Admin endpoint)
+        /// Only the owner of the contract may revoke roles
+        #[ink(message)]
+        pub fn revoke_role(&mut self, role: Role, account: AccountId) -> Result<(), Error> {
+            self.ensure_owner()?;
+
+            self.roles.remove((role, account));
+            self.env().emit_event(RoleRevoked {
+                account,
+                role,
+                revoked_by: self.env().caller(),
+            });
+
+            Ok(())
+        }
+
+        /// Returns true if the account was granted the role (General purpose endpoint)
+        #[ink(message)]
+        pub fn has_role(&self, role: Role, account: AccountId) -> bool {
+            self.roles.contains((role, account))
+        }
+
+        /// Turn permissioned mode on or off (Admin endpoint)
+        /// While permissioned only accounts with the Worker role may claim tasks
+        /// Only the owner of the contract may change it
+        #[ink(message)]
+        pub fn set_permissioned(&mut self, permissioned: bool) -> Result<(), Error> {
+            self.ensure_owner()?;
+
+            self.permissioned.set(&permissioned);
+            self.config_changed(Setting::Permissioned(permissioned));
+
+            Ok(())
+        }
+
+        /// Returns true if the contract is permissioned (General purpose endpoint)
+        #[ink(message)]
+        pub fn is_permissioned(&self) -> bool {
+            self.permissioned.get().unwrap_or_default()
+        }
+
+        /// Set the challenge period of accepted results (Admin endpoint)
+        /// Results are paid right away when it is 0
+        /// Only the owner or an operator may change it
+        #[ink(message)]
+        pub fn set_challenge_period(&mut self, challenge_period: Timestamp) -> Result<(), Error> {
+            self.ensure_role(Role::Operator)?;
+
+            self.challenge_period.set(&challenge_period);
+            self.config_changed(Setting::ChallengePeriod(challenge_period));
+
+            Ok(())
+        }
+
+        /// Set the reveal window of committed results (Admin endpoint)
+        /// Only the owner or an operator may change it
+        #[ink(message)]
+        pub fn set_reveal_window(&mut self, reveal_window: Timestamp) -> Result<(), Error> {
+            self.ensure_role(Role::Operator)?;
+
+            self.reveal_window.set(&reveal_window);
+            self.config_changed(Setting::RevealWindow(reveal_window));
+
+            Ok(())
+        }
+
+        /// Fetch the reveal window of committed results (General purpose endpoint)
+        #[ink(message)]
+        pub fn get_reveal_window(&self) -> Timestamp {
+            self.reveal_window.get().unwrap_or(DEFAULT_REVEAL_WINDOW)
+        }
+
+        /// Set the number of tasks a worker may hold in progress at once (Admin endpoint)
+        /// Applies to workers that did not declare their own capacity
+        /// Only the owner or an operator may change it
+        #[ink(message)]
+        pub fn set_default_concurrency(&mut self, concurrency: u32) -> Result<(), Error> {
+            self.ensure_role(Role::Operator)?;
+
+            if concurrency == 0 {
+                return Err(Error::InvalidConfig);
+            }
+
+            self.default_concurrency.set(&concurrency);
+            self.config_changed(Setting::DefaultConcurrency(concurrency));
+
+            Ok(())
+        }
+
+        /// Fetch the number of tasks a worker may hold in progress at once by default (General purpose endpoint)
+        #[ink(message)]
+        pub fn get_default_concurrency(&self) -> u32 {
+            self.default_concurrency
+                .get()
+                .unwrap_or(DEFAULT_CONCURRENCY)
+        }
+
+        /// Fetch the challenge period of accepted results (General purpose endpoint)
+        #[ink(message)]
+        pub fn get_challenge_period(&self) -> Timestamp {
+            self.challenge_period.get().unwrap_or_default()
+        }
+
+        /// Returns an error if the caller is neither the owner nor holds the role
+        fn ensure_role(&self, role: Role) -> Result<(), Error> {
+            let caller = self.env().caller();
+            if self.owner != Some(caller) && !self.has_role(role, caller) {
+                return Err(Error::MissingRole);
+            }
+
+            Ok(())
+        }
+
+        /// Returns an error if the contract is paused
+        fn ensure_not_paused(&self) -> Result<(), Error> {
+            if self.paused {
+                return Err(Error::Paused);
+            }
+
+            Ok(())
+        }
+
+        /// Returns an error if the caller is neither the owner, the guardian nor an operator
+        fn ensure_guardian(&self) -> Result<(), Error> {
+            let caller = self.env().caller();
+            if Some(caller) != self.guardian && self.ensure_role(Role::Operator).is_err() {
+                return Err(Error::NotGuardian);
+            }
+
+            Ok(())
+        }
+
+        /// Returns an error if the caller is not the owner of the contract
+        fn ensure_owner(&self) -> Result<(), Error> {
+            if self.owner != Some(self.env().caller()) {
+                return Err(Error::NotContractOwner);
+            }
+
+            Ok(())
+        }
+
+        /// Emit the ConfigChanged event for a setting changed by the caller
+        fn config_changed(&self, setting: Setting) {
+            self.env().emit_event(ConfigChanged {
+                changed_by: self.env().caller(),
+                setting,
+            });
+        }
+
+        /// Find the first job of an open job queue the worker may claim
+        /// Looks at up to `MAX_QUEUE_SCAN` entries, dropping jobs that expired while queued
+        fn first_open_job(
+            &mut self,
+            queue: u8,
+            worker: &Worker,
+            reputation: u32,
+            now: Timestamp,
+        ) -> Option<Job> {
+            let head = self.open_head[queue as usize];
+            let end = self.open_tail[queue as usize].min(head.saturating_add(MAX_QUEUE_SCAN));
+
+            for position in head..end {
+                let Some(job_id) = self.open_jobs.get((queue, position)) else {
+                    continue;
+                };
+                let Some(job) = self.load_job(job_id) else {
+                    continue;
+                };
+
+                if !job.is_open(now) {
+                    self.dequeue_job(job_id);
+                    continue;
+                }
+
+                if job.accepts(worker, reputation).is_ok()
+                    && job.claimable_task(self.env().caller()).is_some()
+                {
+                    return Some(job);
+                }
+            }
+
+            None
+        }
+
+        /// Keep the open job queues in step with a job after its tasks changed
+        fn sync_open_job(&mut self, job: &Job) {
+            if job.is_open(self.env().block_timestamp()) {
+                self.enqueue_job(job);
             } else {
-                JobStatus::Completed
+                self.dequeue_job(job.id);
+            }
+        }
+
+        /// Add a job to the back of its open job queue, unless it is already queued
+        /// A job claimable again after a failed or reclaimed task goes to the back as well
+        fn enqueue_job(&mut self, job: &Job) {
+            if self.open_job_position.contains(job.id) {
+                return;
+            }
+
+            let queue = job.queue();
+            let position = self.open_tail[queue as usize];
+            self.open_jobs.insert((queue, position), &job.id);
+            self.open_job_position.insert(job.id, &(queue, position));
+            self.open_tail[queue as usize] = position.saturating_add(1);
+        }
+
+        /// Remove a job from the open job queues
+        fn dequeue_job(&mut self, job_id: u32) {
+            let Some((queue, position)) = self.open_job_position.take(job_id) else {
+                return;
+            };
+            self.open_jobs.remove((queue, position));
+
+            // Move the front past removed entries
+            let index = queue as usize;
+            while self.open_head[index] < self.open_tail[index]
+                && !self.open_jobs.contains((queue, self.open_head[index]))
+            {
+                self.open_head[index] = self.open_head[index].saturating_add(1);
+            }
+        }
+
+        /// Pass the outcome of a job on to the jobs depending on it
+        /// Once the job has completed its dependents stop waiting for it,
+        /// once it can no longer complete, having failed or expired, they fail too
+        fn update_dependents(&mut self, job: &Job) -> Result<(), Error> {
+            if job.get_job_status() == JobStatus::Completed {
+                self.release_dependents(job.id);
+                Ok(())
+            } else if !job.can_complete() {
+                self.detach_dependent(job);
+                self.fail_dependents(job.id)
+            } else {
+                Ok(())
+            }
+        }
+
+        /// Count down the pending parents of the jobs waiting for a completed job
+        /// Dependents left without pending parents become claimable
+        fn release_dependents(&mut self, job_id: u32) {
+            for dependent_id in self.dependents.take(job_id).unwrap_or_default() {
+                let Some(mut dependent) = self.load_job(dependent_id) else {
+                    continue;
+                };
+                if dependent.get_job_status() == JobStatus::Failed {
+                    continue;
+                }
+
+                dependent.pending_parents = dependent.pending_parents.saturating_sub(1);
+                self.save_job(&dependent);
+
+                if dependent.pending_parents == 0 {
+                    self.sync_open_job(&dependent);
+                    self.env().emit_event(JobRunnable {
+                        job_id: dependent_id,
+                    });
+                }
+            }
+        }
+
+        /// Fail the jobs waiting for a job that can no longer complete, and the jobs waiting for those
+        /// The escrow of every failed dependent is refunded to its creator
+        /// At most `MAX_CASCADE` jobs fail in one call, the failed jobs keep the dependents left over
+        /// so `notify_dependents` can carry on from them
+        fn fail_dependents(&mut self, job_id: u32) -> Result<(), Error> {
+            let mut budget = MAX_CASCADE;
+            let mut failed = Vec::from([job_id]);
+            while let Some(parent_id) = failed.pop() {
+                let mut dependents = self.dependents.take(parent_id).unwrap_or_default();
+                while let Some(dependent_id) = dependents.pop() {
+                    if budget == 0 {
+                        dependents.push(dependent_id);
+                        self.dependents.insert(parent_id, &dependents);
+                        return Ok(());
+                    }
+
+                    let Some(mut dependent) = self.load_job(dependent_id) else {
+                        continue;
+                    };
+                    if dependent.get_job_status() == JobStatus::Failed {
+                        continue;
+                    }
+                    budget = budget.saturating_sub(1);
+
+                    dependent.updated_at = self.env().block_timestamp().into();
+                    let refund = self.close_job(&mut dependent);
+                    self.save_job(&dependent);
+                    self.sync_open_job(&dependent);
+                    self.detach_dependent(&dependent);
+
+                    self.env().emit_event(DependencyFailed {
+                        job_id: dependent_id,
+                        parent_id,
+                    });
+                    self.refund(dependent_id, dependent.creator, refund)?;
+
+                    failed.push(dependent_id);
+                }
+            }
+
+            Ok(())
+        }
+
+        /// Remove a job that can no longer complete from the dependents of its parents,
+        /// so it no longer takes up one of their `MAX_DEPENDENTS` places
+        fn detach_dependent(&mut self, job: &Job) {
+            for &parent_id in job.parents.iter() {
+                let Some(mut dependents) = self.dependents.get(parent_id) else {
+                    continue;
+                };
+                dependents.retain(|&dependent_id| dependent_id != job.id);
+                if dependents.is_empty() {
+                    self.dependents.remove(parent_id);
+                } else {
+                    self.dependents.insert(parent_id, &dependents);
+                }
+            }
+        }
+
+        /// Fail every unfinished task of a job and take the refund for them
+        /// Workers still holding a task of the job are released from it
+        fn close_job(&mut self, job: &mut Job) -> Balance {
+            for task in job
+                .tasks
+                .iter()
+                .filter(|task| task.status == JobStatus::InProgress)
+            {
+                if let Some(worker) = task.worker {
+                    self.untrack_task(worker, job.id, task.id);
+                }
+            }
+
+            job.completed_at = self.env().block_timestamp().into();
+            job.fail_remaining();
+            job.take_refund()
+        }
+
+        /// Fetch a registered worker allowed to claim tasks
+        /// Returns an error if the account is not registered or its stake is too low
+        fn check_worker(&self, account: AccountId) -> Result<Worker, Error> {
+            let worker = self.workers.get(account).ok_or(Error::NotRegistered)?;
+
+            if self.is_permissioned() && !self.has_role(Role::Worker, account) {
+                return Err(Error::MissingRole);
+            }
+
+            if worker.stake < self.min_stake {
+                return Err(Error::InsufficientStake);
+            }
+
+            Ok(worker)
+        }
+
+        /// Slash part of a worker's stake for a failed or abandoned task
+        /// The slashed amount is paid to the creator of the job
+        /// Returns the amount slashed
+        fn slash(
+            &mut self,
+            account: AccountId,
+            job_id: u32,
+            task_id: u32,
+            creator: AccountId,
+        ) -> Result<Balance, Error> {
+            let Some(mut worker) = self.workers.get(account) else {
+                return Ok(0);
+            };
+
+            let amount = worker
+                .stake
+                .saturating_mul(Balance::from(self.slash_bps))
+                .checked_div(BPS)
+                .unwrap_or_default();
+            if amount == 0 {
+                return Ok(0);
+            }
+
+            worker.stake = worker.stake.saturating_sub(amount);
+            self.workers.insert(account, &worker);
+
+            self.transfer(creator, amount)?;
+            self.env().emit_event(WorkerSlashed {
+                worker: account,
+                job_id,
+                task_id,
+                amount,
+            });
+
+            Ok(amount)
+        }
+
+        /// Transfer funds held in escrow by the contract to an account
+        /// Zero amounts are skipped
+        fn transfer(&self, to: AccountId, amount: Balance) -> Result<(), Error> {
+            if amount == 0 {
+                return Ok(());
+            }
+
+            self.env()
+                .transfer(to, amount)
+                .map_err(|_| Error::PayoutFailed)
+        }
+
+        /// Pay a worker the reward of an accepted task and credit its stats
+        fn pay(
+            &mut self,
+            job_id: u32,
+            task_id: u32,
+            worker: AccountId,
+            reward: Balance,
+        ) -> Result<(), Error> {
+            let mut stats = self.get_worker_stats(worker);
+            stats.completed = stats.completed.saturating_add(1);
+            stats.earnings = stats.earnings.saturating_add(reward);
+            self.stats.insert(worker, &stats);
+
+            self.transfer(worker, reward)?;
+            if reward > 0 {
+                self.env().emit_event(TaskPaid {
+                    worker,
+                    job_id,
+                    task_id,
+                    amount: reward,
+                });
             }
+
+            Ok(())
         }
 
-        /// Claim a task
-        /// If job has available tasks, claim the first available task
-        /// Returns an error if no tasks are available
-        pub fn claim_task(&mut self, worker: AccountId) -> Result<Task, Error> {
-            // Claim first "Created" task
-            for task in self.tasks.iter_mut() {
-                if task.status == JobStatus::Created {
-                    task.worker = Some(worker);
-                    task.status = JobStatus::InProgress;
-                    task.updated_at = Some(block_timestamp::<DefaultEnvironment>());
-                    return Ok(task.clone());
-                }
+        /// Return unspent escrow to the creator of a job
+        fn refund(&self, job_id: u32, creator: AccountId, amount: Balance) -> Result<(), Error> {
+            if amount == 0 {
+                return Ok(());
             }
 
-            Err(Error::ClaimFailed)
+            self.transfer(creator, amount)?;
+            self.env().emit_event(JobRefunded {
+                creator,
+                job_id,
+                amount,
+            });
+
+            Ok(())
         }
+    }
 
-        /// Scan active tasks for account if, returns True if account id is found
-        pub fn has_worker(&self, worker: AccountId, task_status: Option<JobStatus>) -> bool {
-            let task_status = task_status.unwrap_or(JobStatus::InProgress);
+    /// Unit tests in Rust are normally defined within such a `#[cfg(test)]`
+    /// module and test functions are marked with a `#[test]` attribute.
+    /// The below code is technically just normal Rust code.
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use ink::env::test::{self, DefaultAccounts};
 
-            for task in self.tasks.iter().filter(|task| task.status == task_status) {
-                if let Some(worker_id) = task.worker {
-                    if worker_id == worker {
-                        return true;
-                    }
-                }
-            }
+        /// Deploy the contract under its own account, funded for payouts
+        /// Bob and Charlie are registered as workers, Alice is the caller
+        fn setup() -> (NocturneJob, DefaultAccounts<DefaultEnvironment>) {
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            test::set_callee::<DefaultEnvironment>(accounts.django);
+            test::set_account_balance::<DefaultEnvironment>(accounts.django, 1_000_000_000_000_000);
 
-            false
+            let mut contract = NocturneJob::default();
+            register(&mut contract, accounts.bob);
+            register(&mut contract, accounts.charlie);
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+
+            (contract, accounts)
         }
-    }
 
-    /// Defines the storage of your contract.
-    /// Add new fields to the below struct in order
-    /// to add new static storage fields to your contract.
-    #[ink(storage)]
-    pub struct NocturneJob {
-        /// Store listing of all active jobs
-        jobs: StorageVec<Job>,
+        /// Register an account as a worker with the minimum stake
+        fn register(contract: &mut NocturneJob, account: AccountId) {
+            test::set_caller::<DefaultEnvironment>(account);
+            test::set_value_transferred::<DefaultEnvironment>(DEFAULT_MIN_STAKE);
+            contract.register_worker(Capabilities::default()).unwrap();
+            test::set_value_transferred::<DefaultEnvironment>(0);
+        }
 
-        /// Max retries for a task
-        max_retries: u32,
+        /// We test if the default constructor does its job.
+        #[ink::test]
+        fn deploy_contact_default() {
+            let contract = NocturneJob::default();
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert_eq!(contract.owner, Some(accounts.alice));
+            assert_eq!(contract.max_retries, 3);
+            assert_eq!(contract.max_tasks, 15);
+            assert_eq!(contract.next_job_id, 0);
+        }
 
-        /// Max tasks a job may have
-        max_tasks: u32,
-    }
-    //-- End of Storage
+        /// Test Job status transitions and reporting
+        #[ink::test]
+        fn job_status() {
+            let mut job = Job::new(account_id::<DefaultEnvironment>(), "cid".to_string(), 3);
 
-    impl NocturneJob {
-        /// Constructor that initializes jobs from externally provided StorageVec.
-        #[ink(constructor)]
-        pub fn new(max_retries: u32, max_tasks: u32) -> Self {
-            let jobs: StorageVec<Job, _> = StorageVec::new();
+            // Job is created
+            assert_eq!(
+                job.get_job_status(),
+                JobStatus::Created,
+                "Job is created if all tasks are created ({:?}|{:?}|{:?})",
+                job.tasks[0].status,
+                job.tasks[1].status,
+                job.tasks[2].status
+            );
 
-            if max_retries == 0 || max_tasks == 0 {
-                panic!("Max retries and max tasks must be greater than 0");
-            }
+            // Job is in progress
+            job.tasks[0].status = JobStatus::InProgress;
+            job.tasks[1].status = JobStatus::Created;
+            job.tasks[2].status = JobStatus::Completed;
+            assert_eq!(
+                job.get_job_status(),
+                JobStatus::InProgress,
+                "Job is in progress if any task is in progress ({:?}|{:?}|{:?})",
+                job.tasks[0].status,
+                job.tasks[1].status,
+                job.tasks[2].status
+            );
 
-            Self {
-                jobs,
-                max_retries,
-                max_tasks,
-            }
+            // Job is failed due to task failure
+            job.tasks[0].status = JobStatus::Failed;
+            job.tasks[1].status = JobStatus::InProgress;
+            job.tasks[2].status = JobStatus::Created;
+            assert_eq!(
+                job.get_job_status(),
+                JobStatus::Failed,
+                "Job is failed if any task is failed ({:?}|{:?}|{:?})",
+                job.tasks[0].status,
+                job.tasks[1].status,
+                job.tasks[2].status
+            );
+
+            // Job is completed
+            job.tasks[0].status = JobStatus::Completed;
+            job.tasks[1].status = JobStatus::Completed;
+            job.tasks[2].status = JobStatus::Completed;
+            assert_eq!(
+                job.get_job_status(),
+                JobStatus::Completed,
+                "Job is completed if all tasks are completed ({:?}|{:?}|{:?})",
+                job.tasks[0].status,
+                job.tasks[1].status,
+                job.tasks[2].status
+            );
         }
 
-        /// The default constructor
-        #[ink(constructor, default)]
-        pub fn default() -> Self {
-            Self {
-                jobs: Default::default(),
-                max_retries: 3,
-                max_tasks: 15,
-            }
+        /// Test that the tip is split across tasks and released on completion
+        #[ink::test]
+        fn complete_pays_worker() {
+            let (mut contract, accounts) = setup();
+
+            // Alice submits a job with 3 tasks and a tip of 100
+            test::set_value_transferred::<DefaultEnvironment>(100);
+            contract
+                .submit("cid".to_string(), 3, JobOptions::default())
+                .unwrap();
+            test::set_value_transferred::<DefaultEnvironment>(0);
+
+            let job = contract.get_job(0).unwrap().unwrap();
+            assert_eq!(job.id, 0);
+            assert_eq!(job.escrow, 100);
+            assert_eq!(job.tasks[0].reward, 34);
+            assert_eq!(job.tasks[1].reward, 33);
+            assert_eq!(job.tasks[2].reward, 33);
+
+            // Bob claims and completes the first task
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            let balance = test::get_account_balance::<DefaultEnvironment>(accounts.bob).unwrap();
+            contract.claim(0).unwrap();
+            contract.complete(0, 0, "result".to_string()).unwrap();
+
+            assert_eq!(
+                test::get_account_balance::<DefaultEnvironment>(accounts.bob).unwrap(),
+                balance + 34
+            );
+            assert_eq!(contract.get_job(0).unwrap().unwrap().escrow, 66);
         }
 
-        /// Submit a new job (UI endpoint)
-        /// The job is created and added to the jobs storage
-        /// The job is created with the creator account id, the data content id and the manifest content id
-        /// The job is created with the status Created
-        /// The job is created with the created_at timestamp
-        /// Submit is a payable function, the caller may attach a deposit to the call. Deposit is a 'tip' for the worker
-        #[ink(message, payable)]
-        pub fn submit(&mut self, cid_manifest: String, task_count: u32) -> Result<(), Error> {
-            let creator = self.env().caller();
+        /// Test that cancelling a job refunds the share of unfinished tasks
+        #[ink::test]
+        fn cancel_refunds_creator() {
+            let (mut contract, accounts) = setup();
 
-            if task_count == 0 || task_count > 10 {
-                return Err(Error::SubmitFailed);
-            }
+            test::set_value_transferred::<DefaultEnvironment>(90);
+            contract
+                .submit("cid".to_string(), 3, JobOptions::default())
+                .unwrap();
+            test::set_value_transferred::<DefaultEnvironment>(0);
 
-            // Tip is None if 0 value is transferred else the value transferred
-            let transfered_value = self.env().transferred_value();
-            let tip = if transfered_value == 0 {
-                None
-            } else {
-                Some(transfered_value)
-            };
+            // Bob completes one task
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            contract.claim(0).unwrap();
+            contract.complete(0, 0, "result".to_string()).unwrap();
 
-            let tasks = vec![Task::default(); task_count as usize];
+            // Alice cancels the job and gets back the two remaining shares
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            let balance = test::get_account_balance::<DefaultEnvironment>(accounts.alice).unwrap();
+            contract.cancel(0).unwrap();
 
-            let job = Job {
-                creator: self.env().caller(),
-                cid_manifest,
-                cid_results: None,
-                tip,
-                tasks,
-                created_at: self.env().block_timestamp(),
-                updated_at: None,
-                completed_at: None,
+            assert_eq!(
+                test::get_account_balance::<DefaultEnvironment>(accounts.alice).unwrap(),
+                balance + 60
+            );
+
+            let job = contract.get_job(0).unwrap().unwrap();
+            assert_eq!(job.escrow, 0);
+            assert_eq!(job.tasks[0].status, JobStatus::Completed);
+            assert_eq!(job.get_job_status(), JobStatus::Failed);
+        }
+
+        /// Test that anyone can reclaim a task once its lease expired
+        #[ink::test]
+        fn reclaim_expired_lease() {
+            let (mut contract, accounts) = setup();
+            contract
+                .submit("cid".to_string(), 1, JobOptions::default())
+                .unwrap();
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            contract.claim(0).unwrap();
+
+            // Heartbeat extends the lease
+            test::set_block_timestamp::<DefaultEnvironment>(DEFAULT_LEASE_DURATION);
+            assert_eq!(contract.heartbeat(0, 0), Ok(DEFAULT_LEASE_DURATION * 2));
+
+            // Lease still held, nothing to reclaim
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            test::set_block_timestamp::<DefaultEnvironment>(DEFAULT_LEASE_DURATION * 2);
+            assert_eq!(contract.reclaim_expired(0), Ok(0));
+
+            // Lease expired, task goes back to Created
+            test::set_block_timestamp::<DefaultEnvironment>(DEFAULT_LEASE_DURATION * 2 + 1);
+            assert_eq!(contract.reclaim_expired(0), Ok(1));
+
+            let job = contract.get_job(0).unwrap().unwrap();
+            let task = &job.tasks[0];
+            assert_eq!(task.status, JobStatus::Created);
+            assert_eq!(task.worker, None);
+            assert_eq!(task.retries, 1);
+
+            // Expired worker can no longer heartbeat the task
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.heartbeat(0, 0), Err(Error::NotWorker));
+        }
+
+        /// Test that a job past its deadline expires and its escrow can be withdrawn
+        #[ink::test]
+        fn expired_job_withdraw() {
+            let (mut contract, accounts) = setup();
+
+            test::set_value_transferred::<DefaultEnvironment>(100);
+            contract
+                .submit(
+                    "cid".to_string(),
+                    2,
+                    JobOptions {
+                        deadline: Some(10),
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
+            test::set_value_transferred::<DefaultEnvironment>(0);
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            contract.claim(0).unwrap();
+
+            // Deadline passed, remaining task can no longer be claimed
+            test::set_block_timestamp::<DefaultEnvironment>(11);
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            assert_eq!(contract.claim(0), Err(Error::JobExpired));
+
+            let job = contract.get_job(0).unwrap().unwrap();
+            assert_eq!(job.get_job_status(), JobStatus::Expired);
+
+            // Escrow is locked while bob still holds a task
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.withdraw_expired(0), Err(Error::TasksInProgress));
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            contract.complete(0, 0, "result".to_string()).unwrap();
+
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.withdraw_expired(0), Ok(50));
+            assert_eq!(contract.get_job(0).unwrap().unwrap().escrow, 0);
+        }
+
+        /// Test that jobs waiting for an expired job fail instead of waiting forever
+        #[ink::test]
+        fn expired_parent() {
+            let (mut contract, accounts) = setup();
+
+            contract
+                .submit(
+                    "cid".to_string(),
+                    1,
+                    JobOptions {
+                        deadline: Some(10),
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
+            let options = JobOptions {
+                parents: vec![0],
+                ..Default::default()
             };
+            test::set_value_transferred::<DefaultEnvironment>(50);
+            contract
+                .submit("cid".to_string(), 1, options.clone())
+                .unwrap();
+            test::set_value_transferred::<DefaultEnvironment>(0);
+
+            // Nobody claimed the parent before its deadline, it can no longer complete
+            test::set_block_timestamp::<DefaultEnvironment>(11);
+            let parent = contract.get_job(0).unwrap().unwrap();
+            assert_eq!(parent.get_job_status(), JobStatus::Expired);
+            assert!(!parent.can_complete());
+            assert_eq!(
+                contract.submit("cid".to_string(), 1, options),
+                Err(Error::SubmitFailed)
+            );
+
+            // Withdrawing the expired job fails and refunds its dependent
+            let balance = test::get_account_balance::<DefaultEnvironment>(accounts.alice).unwrap();
+            contract.withdraw_expired(0).unwrap();
+            let job = contract.get_job(1).unwrap().unwrap();
+            assert_eq!(job.get_job_status(), JobStatus::Failed);
+            assert_eq!(
+                test::get_account_balance::<DefaultEnvironment>(accounts.alice).unwrap(),
+                balance + 50
+            );
+        }
 
-            self.jobs
-                .try_push::<Job>(&job)
-                .map_err(|_| Error::SubmitFailed)?;
+        /// Test that only staked workers can claim and stakes unlock after the cooldown
+        #[ink::test]
+        fn worker_registry() {
+            let (mut contract, accounts) = setup();
+            contract
+                .submit("cid".to_string(), 1, JobOptions::default())
+                .unwrap();
+
+            // Eve is not registered
+            test::set_caller::<DefaultEnvironment>(accounts.eve);
+            assert_eq!(contract.claim(0), Err(Error::NotRegistered));
+            assert_eq!(contract.claim_first(), Err(Error::NotRegistered));
+
+            // Stake below the minimum is rejected
+            test::set_value_transferred::<DefaultEnvironment>(DEFAULT_MIN_STAKE - 1);
+            assert_eq!(
+                contract.register_worker(Capabilities::default()),
+                Err(Error::InsufficientStake)
+            );
 
-            let job_id = self.jobs.len().checked_sub(1).ok_or(Error::SubmitFailed)?;
-            self.env().emit_event(JobSubmitted { creator, job_id });
+            test::set_value_transferred::<DefaultEnvironment>(DEFAULT_MIN_STAKE);
+            assert_eq!(contract.register_worker(Capabilities::default()), Ok(()));
+            test::set_value_transferred::<DefaultEnvironment>(0);
+            assert_eq!(
+                contract.get_worker(accounts.eve).map(|worker| worker.stake),
+                Some(DEFAULT_MIN_STAKE)
+            );
 
-            Ok(())
+            // Eve can claim once registered, but can't leave while busy or cooling down
+            contract.claim(0).unwrap();
+            assert_eq!(contract.unregister_worker(), Err(Error::CooldownActive));
+
+            test::set_block_timestamp::<DefaultEnvironment>(DEFAULT_WORKER_COOLDOWN);
+            assert_eq!(contract.unregister_worker(), Err(Error::CallerBusy));
+
+            contract.complete(0, 0, "result".to_string()).unwrap();
+            assert_eq!(contract.unregister_worker(), Ok(DEFAULT_MIN_STAKE));
+            assert_eq!(contract.get_worker(accounts.eve), None);
         }
 
-        /// Claim a job (Backend endpoint)
-        /// The job is updated with the status Pending
-        /// The job is updated with the updated_at timestamp
-        /// The job is updated with the worker account id
-        #[ink(message)]
-        pub fn claim(&mut self, job_id: u32) -> Result<Job, Error> {
-            let caller = self.env().caller();
+        /// Test that failing a task slashes the worker in favour of the creator
+        #[ink::test]
+        fn fail_slashes_worker() {
+            let (mut contract, accounts) = setup();
+            contract
+                .submit("cid".to_string(), 1, JobOptions::default())
+                .unwrap();
+            let balance = test::get_account_balance::<DefaultEnvironment>(accounts.alice).unwrap();
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            contract.claim(0).unwrap();
+            contract.fail(0, 0).unwrap();
+
+            let slashed = DEFAULT_MIN_STAKE / 10;
+            assert_eq!(
+                contract.get_worker(accounts.bob).map(|worker| worker.stake),
+                Some(DEFAULT_MIN_STAKE - slashed)
+            );
+            assert_eq!(
+                test::get_account_balance::<DefaultEnvironment>(accounts.alice).unwrap(),
+                balance + slashed
+            );
+
+            // Bob is now below the minimum stake and can't claim again
+            assert_eq!(contract.claim(0), Err(Error::InsufficientStake));
+        }
+
+        /// Test that completed tasks build reputation and unlock demanding jobs
+        #[ink::test]
+        fn reputation_gates_claim() {
+            let (mut contract, accounts) = setup();
+            test::set_value_transferred::<DefaultEnvironment>(10);
+            let options = JobOptions {
+                min_reputation: 1,
+                ..Default::default()
+            };
+            assert_eq!(contract.submit("cid".to_string(), 1, options), Ok(0));
+            assert_eq!(
+                contract.submit("cid".to_string(), 1, JobOptions::default()),
+                Ok(1)
+            );
+            test::set_value_transferred::<DefaultEnvironment>(0);
+
+            // Bob has no track record, first job is out of reach
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.claim(0), Err(Error::ReputationTooLow));
 
-            // If caller is already working a task in any job, return error
-            if let Some(jobs) = self.get_jobs_by_worker(Some(JobStatus::InProgress)) {
-                if !jobs.is_empty() {
-                    return Err(Error::CallerBusy);
+            let job = contract.claim_first().unwrap();
+            assert_eq!(job.id, 1);
+            contract.complete(1, 0, "result".to_string()).unwrap();
+
+            assert_eq!(
+                contract.get_worker_stats(accounts.bob),
+                WorkerStats {
+                    completed: 1,
+                    failed: 0,
+                    expired: 0,
+                    earnings: 10,
+                    rejected: 0,
                 }
+            );
+
+            // One completed task is enough for the first job
+            assert!(contract.claim(0).is_ok());
+        }
+
+        /// Test that every task keeps its own id and result
+        #[ink::test]
+        fn task_results_in_order() {
+            let (mut contract, accounts) = setup();
+            contract
+                .submit("cid".to_string(), 2, JobOptions::default())
+                .unwrap();
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            contract.claim(0).unwrap();
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            let job = contract.claim(0).unwrap();
+            assert_eq!(job.tasks[0].id, 0);
+            assert_eq!(job.tasks[1].id, 1);
+            assert_eq!(job.tasks[1].worker, Some(accounts.charlie));
+
+            // Results are kept per task and collected in task order
+            contract.complete(0, 1, "result-1".to_string()).unwrap();
+            let job = contract.get_job(0).unwrap().unwrap();
+            assert_eq!(job.tasks[1].cid_result, Some("result-1".to_string()));
+            assert_eq!(job.cid_results, None);
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            contract.complete(0, 0, "result-0".to_string()).unwrap();
+            let job = contract.get_job(0).unwrap().unwrap();
+            assert_eq!(
+                job.cid_results,
+                Some(vec!["result-0".to_string(), "result-1".to_string()])
+            );
+        }
+
+        /// Test that replicated tasks are only paid once a quorum of workers agree
+        #[ink::test]
+        fn redundant_quorum() {
+            let (mut contract, accounts) = setup();
+            register(&mut contract, accounts.eve);
+
+            // One task run by three workers, two of them must agree
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            let options = JobOptions {
+                replicas: 3,
+                quorum: 2,
+                ..Default::default()
+            };
+            assert_eq!(
+                contract.submit(
+                    "cid".to_string(),
+                    1,
+                    JobOptions {
+                        quorum: 1,
+                        ..options.clone()
+                    }
+                ),
+                Err(Error::SubmitFailed)
+            );
+            test::set_value_transferred::<DefaultEnvironment>(300);
+            contract.submit("cid".to_string(), 1, options).unwrap();
+            test::set_value_transferred::<DefaultEnvironment>(0);
+
+            // A worker runs a single replica of each task
+            let salt = Hash::from([7; 32]);
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            let job = contract.claim(0).unwrap();
+            assert_eq!(job.claimable_task(accounts.bob), None);
+            assert_eq!(job.claimable_task(accounts.charlie), Some(1));
+
+            // Replicated results are committed first and revealed once every replica committed
+            assert_eq!(
+                contract.complete(0, 0, "wrong".to_string()),
+                Err(Error::CommitRequired)
+            );
+            contract
+                .commit_result(0, 0, result_commitment("wrong", &salt, &accounts.bob))
+                .unwrap();
+            for (task_id, worker) in [(1, accounts.charlie), (2, accounts.eve)] {
+                test::set_caller::<DefaultEnvironment>(worker);
+                contract.claim(0).unwrap();
+                contract
+                    .commit_result(0, task_id, result_commitment("result", &salt, &worker))
+                    .unwrap();
             }
 
-            // If job has available tasks, claim the first available task
-            let mut job = self.jobs.get(job_id).ok_or(Error::ClaimFailed)?;
-            let task = job.claim_task(caller).map_err(|_| Error::ClaimFailed)?;
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            contract
+                .reveal_result(0, 0, "wrong".to_string(), salt)
+                .unwrap();
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            contract
+                .reveal_result(0, 1, "result".to_string(), salt)
+                .unwrap();
+            let job = contract.get_job(0).unwrap().unwrap();
+            assert!(!job.tasks[1].paid);
+            assert_eq!(job.escrow, 300);
+
+            // The second matching result reaches the quorum, the mismatching worker is rejected
+            let alice = test::get_account_balance::<DefaultEnvironment>(accounts.alice).unwrap();
+            let charlie =
+                test::get_account_balance::<DefaultEnvironment>(accounts.charlie).unwrap();
+            test::set_caller::<DefaultEnvironment>(accounts.eve);
+            contract
+                .reveal_result(0, 2, "result".to_string(), salt)
+                .unwrap();
+
+            let job = contract.get_job(0).unwrap().unwrap();
+            assert!(job.tasks[0].rejected);
+            assert!(job.tasks[1].paid && job.tasks[2].paid);
+            assert_eq!(job.escrow, 0);
+            assert_eq!(job.cid_results, Some(vec!["result".to_string()]));
+            assert_eq!(job.summary().status, JobStatus::Completed);
+            assert_eq!(
+                test::get_account_balance::<DefaultEnvironment>(accounts.charlie).unwrap(),
+                charlie + 100
+            );
+            assert_eq!(
+                test::get_account_balance::<DefaultEnvironment>(accounts.alice).unwrap(),
+                alice + 100
+            );
+            assert_eq!(contract.get_worker_stats(accounts.bob).rejected, 1);
+        }
 
-            job.updated_at = self.env().block_timestamp().into();
+        /// Test that a shard completes once the quorum agrees, without waiting for every replica
+        #[ink::test]
+        fn partial_quorum() {
+            let (mut contract, accounts) = setup();
+            register(&mut contract, accounts.eve);
+
+            // Two of three replicas must agree, only two workers ever show up
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            let options = JobOptions {
+                replicas: 3,
+                quorum: 2,
+                ..Default::default()
+            };
+            test::set_value_transferred::<DefaultEnvironment>(300);
+            contract.submit("cid".to_string(), 1, options).unwrap();
+            test::set_value_transferred::<DefaultEnvironment>(0);
+
+            let salt = Hash::from([7; 32]);
+            for worker in [accounts.bob, accounts.charlie] {
+                test::set_caller::<DefaultEnvironment>(worker);
+                let job = contract.claim(0).unwrap();
+                let task_id = job
+                    .tasks
+                    .iter()
+                    .position(|task| task.worker == Some(worker));
+                contract
+                    .commit_result(
+                        0,
+                        task_id.unwrap() as u32,
+                        result_commitment("result", &salt, &worker),
+                    )
+                    .unwrap();
+            }
 
-            // Update job with the updated task
-            self.jobs.set(job_id, &job).unwrap();
+            test::set_block_timestamp::<DefaultEnvironment>(DEFAULT_REVEAL_WINDOW);
+            let alice = test::get_account_balance::<DefaultEnvironment>(accounts.alice).unwrap();
+            for (task_id, worker) in [(0, accounts.bob), (1, accounts.charlie)] {
+                test::set_caller::<DefaultEnvironment>(worker);
+                contract
+                    .reveal_result(0, task_id, "result".to_string(), salt)
+                    .unwrap();
+            }
 
-            self.env().emit_event(TaskClaimed {
-                worker: caller,
-                task_id: task.id,
-            });
+            // The third replica is closed and its share refunded
+            let job = contract.get_job(0).unwrap().unwrap();
+            assert_eq!(job.get_job_status(), JobStatus::Completed);
+            assert_eq!(job.tasks[2].status, JobStatus::Completed);
+            assert_eq!(job.tasks[2].cid_result, None);
+            assert_eq!(job.cid_results, Some(vec!["result".to_string()]));
+            assert_eq!(job.escrow, 0);
+            assert_eq!(
+                test::get_account_balance::<DefaultEnvironment>(accounts.alice).unwrap(),
+                alice + 100
+            );
+
+            test::set_caller::<DefaultEnvironment>(accounts.eve);
+            assert_eq!(contract.claim(0).err(), Some(Error::ClaimFailed));
+        }
+
+        /// Test that reveals wait for the other replicas and must match their commitment
+        #[ink::test]
+        fn commit_reveal() {
+            let (mut contract, accounts) = setup();
+            let options = JobOptions {
+                replicas: 2,
+                ..Default::default()
+            };
+            contract.submit("cid".to_string(), 1, options).unwrap();
+
+            let salt = Hash::from([7; 32]);
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            contract.claim(0).unwrap();
+            assert_eq!(
+                contract.reveal_result(0, 0, "result".to_string(), salt),
+                Err(Error::NotCommitted)
+            );
+            let commitment = result_commitment("result", &salt, &accounts.bob);
+            assert_eq!(contract.commit_result(0, 0, commitment), Ok(()));
+            assert_eq!(
+                contract.commit_result(0, 0, commitment),
+                Err(Error::AlreadyCommitted)
+            );
+
+            // Charlie holds the other replica but has not committed yet
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            contract.claim(0).unwrap();
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                contract.reveal_result(0, 0, "result".to_string(), salt),
+                Err(Error::RevealNotOpen)
+            );
+
+            // Once the reveal window opens, the other replicas can no longer commit
+            test::set_block_timestamp::<DefaultEnvironment>(DEFAULT_REVEAL_WINDOW);
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            assert_eq!(
+                contract.commit_result(0, 1, result_commitment("result", &salt, &accounts.charlie)),
+                Err(Error::CommitClosed)
+            );
+
+            // Only the committed result is accepted
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                contract.reveal_result(0, 0, "copied".to_string(), salt),
+                Err(Error::CommitmentMismatch)
+            );
+            assert_eq!(
+                contract.reveal_result(0, 0, "result".to_string(), salt),
+                Ok(())
+            );
+
+            // The shard settles on the committed replica, Charlie's replica is closed
+            let job = contract.get_job(0).unwrap().unwrap();
+            assert_eq!(job.get_job_status(), JobStatus::Completed);
+            assert_eq!(job.cid_results, Some(vec!["result".to_string()]));
+            assert!(job.tasks[1].is_closed());
+            assert!(!contract.has_tasks(accounts.charlie));
+        }
+
+        /// Test that workers hold up to their concurrency in tasks and claim them in batches
+        #[ink::test]
+        fn concurrent_claims() {
+            let (mut contract, accounts) = setup();
+            contract
+                .submit("cid".to_string(), 4, JobOptions::default())
+                .unwrap();
+
+            // Eve runs two tasks at once
+            test::set_caller::<DefaultEnvironment>(accounts.eve);
+            test::set_value_transferred::<DefaultEnvironment>(DEFAULT_MIN_STAKE);
+            contract
+                .register_worker(Capabilities {
+                    concurrency: 2,
+                    ..Default::default()
+                })
+                .unwrap();
+            test::set_value_transferred::<DefaultEnvironment>(0);
+            assert_eq!(contract.claim_batch(5), Ok(vec![(0, 0), (0, 1)]));
+            assert_eq!(contract.claim(0).err(), Some(Error::CallerBusy));
+
+            // Bob gets the contract default
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.claim_batch(5), Ok(vec![(0, 2)]));
+            assert_eq!(contract.claim_batch(5), Err(Error::CallerBusy));
+
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                contract.set_default_concurrency(0),
+                Err(Error::InvalidConfig)
+            );
+            assert_eq!(contract.set_default_concurrency(2), Ok(()));
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.claim_batch(5), Ok(vec![(0, 3)]));
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            assert_eq!(contract.claim_batch(5), Err(Error::NoJobs));
+        }
 
-            Ok(job.clone())
+        /// Test that a worker can claim a specific task of a job
+        #[ink::test]
+        fn targeted_claim() {
+            let (mut contract, accounts) = setup();
+            contract
+                .submit("cid".to_string(), 3, JobOptions::default())
+                .unwrap();
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            let job = contract.claim_task(0, 2).unwrap();
+            assert_eq!(job.tasks[2].worker, Some(accounts.bob));
+            assert_eq!(job.tasks[0].status, JobStatus::Created);
+
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            assert_eq!(contract.claim_task(0, 2).err(), Some(Error::TaskTaken));
+            assert_eq!(contract.claim_task(0, 3).err(), Some(Error::ClaimFailed));
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            contract.complete(0, 2, "result".to_string()).unwrap();
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            assert_eq!(contract.claim_task(0, 2).err(), Some(Error::TaskClosed));
+
+            // First-fit claiming is unchanged
+            let job = contract.claim(0).unwrap();
+            assert_eq!(job.tasks[0].worker, Some(accounts.charlie));
         }
 
-        /// Claim first available job (Backend endpoint)
-        /// Scan jobs for most recent available job
-        /// Calls claim with the job id
-        /// Returns an error if no job is available
-        #[ink(message)]
-        pub fn claim_first(&mut self) -> Result<Job, Error> {
-            // If caller is already working a task in any job, return error
-            if let Some(jobs) = self.get_jobs_by_worker(Some(JobStatus::InProgress)) {
-                if !jobs.is_empty() {
-                    return Err(Error::CallerBusy);
-                }
+        /// Test that dependent jobs wait for their parents and fail with them
+        #[ink::test]
+        fn job_dependencies() {
+            let (mut contract, accounts) = setup();
+
+            // Job 1 runs after job 0, job 2 after job 1
+            contract
+                .submit("preprocess".to_string(), 1, JobOptions::default())
+                .unwrap();
+            for parent in 0..2 {
+                let options = JobOptions {
+                    parents: vec![parent],
+                    ..Default::default()
+                };
+                contract.submit("cid".to_string(), 1, options).unwrap();
             }
-
-            let job_count = self.jobs.len();
-            for i in 0..job_count {
-                if let Ok(job) = self.jobs.try_get(i).ok_or(Error::NoJobs)? {
-                    if job.get_job_status() == JobStatus::Created
-                        || job.get_job_status() == JobStatus::InProgress
-                    {
-                        return self.claim(i);
-                    }
-                }
+            test::set_value_transferred::<DefaultEnvironment>(50);
+            let options = JobOptions {
+                parents: vec![0, 2, 2],
+                ..Default::default()
+            };
+            contract.submit("cid".to_string(), 1, options).unwrap();
+            test::set_value_transferred::<DefaultEnvironment>(0);
+            assert_eq!(contract.get_job(3).unwrap().unwrap().parents, vec![0, 2]);
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.claim(1).err(), Some(Error::ParentsPending));
+            assert_eq!(contract.claim_first().unwrap().id, 0);
+            contract.complete(0, 0, "result".to_string()).unwrap();
+
+            // Job 1 becomes runnable, job 2 still waits for it
+            let job = contract.get_job(1).unwrap().unwrap();
+            assert_eq!(job.pending_parents, 0);
+            assert_eq!(contract.get_job(3).unwrap().unwrap().pending_parents, 1);
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            assert_eq!(contract.claim_first().unwrap().id, 1);
+
+            // Cancelling job 1 fails the jobs downstream of it and refunds them
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            let balance = test::get_account_balance::<DefaultEnvironment>(accounts.alice).unwrap();
+            contract.cancel(1).unwrap();
+            for job_id in 2..4 {
+                let job = contract.get_job(job_id).unwrap().unwrap();
+                assert_eq!(job.get_job_status(), JobStatus::Failed);
             }
+            assert_eq!(
+                test::get_account_balance::<DefaultEnvironment>(accounts.alice).unwrap(),
+                balance + 50
+            );
+            let options = JobOptions {
+                parents: vec![1],
+                ..Default::default()
+            };
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                contract.submit("cid".to_string(), 1, options),
+                Err(Error::SubmitFailed)
+            );
 
-            Err(Error::NoJobs)
-        }
-
-        /// Fail a job (Backend endpoint)
-        /// The job is updated with the status Failed
-        /// The job is updated with the updated_at timestamp
-        /// The worker account id is removed from the job workers
-        #[ink(message)]
-        pub fn fail(&mut self, job_id: u32, task_id: u32) -> Result<(), Error> {
-            let caller = self.env().caller();
-            let mut job = self.jobs.get(job_id).ok_or(Error::UpdateFailed)?;
-            let task = job
-                .tasks
-                .get_mut(task_id as usize)
-                .ok_or(Error::UpdateFailed)?;
+            // A job only takes a bounded number of dependents, all from its own creator
+            let parent = contract
+                .submit("cid".to_string(), 1, JobOptions::default())
+                .unwrap();
+            let options = JobOptions {
+                parents: vec![parent],
+                ..Default::default()
+            };
+            test::set_caller::<DefaultEnvironment>(accounts.eve);
+            assert_eq!(
+                contract.submit("cid".to_string(), 1, options.clone()),
+                Err(Error::NotOwner)
+            );
 
-            // If task not owned by caller, return error
-            if task.worker != Some(caller) {
-                return Err(Error::NotWorker);
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            for _ in 0..MAX_DEPENDENTS {
+                contract
+                    .submit("cid".to_string(), 1, options.clone())
+                    .unwrap();
             }
+            assert_eq!(
+                contract.submit("cid".to_string(), 1, options.clone()),
+                Err(Error::SubmitFailed)
+            );
 
-            // If task not in valid in progress state, return error
-            if task.status != JobStatus::InProgress {
-                return Err(Error::UpdateFailed);
+            // A cancelled dependent frees its place
+            contract.cancel(parent + 1).unwrap();
+            assert!(contract.submit("cid".to_string(), 1, options).is_ok());
+        }
+
+        /// Test that a long cascade of failures is spread over several calls
+        #[ink::test]
+        fn bounded_cascade() {
+            let (mut contract, accounts) = setup();
+
+            // A chain of jobs, each waiting for the one before it
+            contract
+                .submit("cid".to_string(), 1, JobOptions::default())
+                .unwrap();
+            for parent in 0..=MAX_CASCADE {
+                let options = JobOptions {
+                    parents: vec![parent],
+                    ..Default::default()
+                };
+                contract.submit("cid".to_string(), 1, options).unwrap();
             }
 
-            // Update task and job
-            task.retries.checked_add(1).ok_or(Error::UpdateFailed)?;
+            // Cancelling the first job fails `MAX_CASCADE` jobs downstream of it
+            contract.cancel(0).unwrap();
+            let job = contract.get_job(MAX_CASCADE).unwrap().unwrap();
+            assert_eq!(job.get_job_status(), JobStatus::Failed);
+            let job = contract.get_job(MAX_CASCADE + 1).unwrap().unwrap();
+            assert_eq!(job.get_job_status(), JobStatus::Created);
+
+            // Anyone carries on from the last failed job
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            contract.notify_dependents(MAX_CASCADE).unwrap();
+            let job = contract.get_job(MAX_CASCADE + 1).unwrap().unwrap();
+            assert_eq!(job.get_job_status(), JobStatus::Failed);
+        }
 
-            // Update job
-            job.updated_at = self.env().block_timestamp().into();
-            if task.retries >= self.max_retries {
-                job.completed_at = self.env().block_timestamp().into();
-                task.status = JobStatus::Failed;
-            } else {
-                task.worker = None;
-                task.status = JobStatus::Created;
-            }
+        /// Test that a map-reduce job creates its reduce task once the map tasks are done
+        #[ink::test]
+        fn map_reduce() {
+            let (mut contract, accounts) = setup();
+            let options = JobOptions {
+                kind: JobKind::MapReduce,
+                ..Default::default()
+            };
+            test::set_value_transferred::<DefaultEnvironment>(300);
+            contract.submit("cid".to_string(), 2, options).unwrap();
+            test::set_value_transferred::<DefaultEnvironment>(0);
+
+            // The tip is split over the two map tasks and the reduce task
+            let job = contract.get_job(0).unwrap().unwrap();
+            assert_eq!(job.tasks.len(), 2);
+            assert_eq!(job.tasks[0].reward, 100);
+            assert_eq!(job.reduce_reward, 100);
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            contract.claim(0).unwrap();
+            contract.complete(0, 0, "map-0".to_string()).unwrap();
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            contract.claim(0).unwrap();
+            contract.complete(0, 1, "map-1".to_string()).unwrap();
+
+            // The reduce task takes the map results as input
+            let job = contract.get_job(0).unwrap().unwrap();
+            assert_eq!(job.get_job_status(), JobStatus::Created);
+            assert_eq!(job.cid_results, None);
+            assert_eq!(job.escrow, 100);
+            assert_eq!(
+                job.tasks[2].cid_inputs,
+                vec!["map-0".to_string(), "map-1".to_string()]
+            );
 
-            // Update job with the updated task
-            self.jobs.set(job_id, &job).unwrap();
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                contract.claim_first().unwrap().tasks[2].worker,
+                Some(accounts.bob)
+            );
+            contract.complete(0, 2, "model".to_string()).unwrap();
 
-            // Emit TaskFailed event
-            self.env().emit_event(TaskFailed {
-                worker: caller,
-                task_id,
-            });
+            let job = contract.get_job(0).unwrap().unwrap();
+            assert_eq!(job.get_job_status(), JobStatus::Completed);
+            assert_eq!(job.escrow, 0);
+            assert_eq!(
+                job.cid_results,
+                Some(vec![
+                    "map-0".to_string(),
+                    "map-1".to_string(),
+                    "model".to_string()
+                ])
+            );
+        }
 
-            Ok(())
+        /// Test that a map-reduce job only starts its reduce once no map result may be disputed
+        #[ink::test]
+        fn map_reduce_challenge() {
+            let (mut contract, accounts) = setup();
+            assert_eq!(contract.set_challenge_period(100), Ok(()));
+            let options = JobOptions {
+                kind: JobKind::MapReduce,
+                ..Default::default()
+            };
+            test::set_value_transferred::<DefaultEnvironment>(300);
+            contract.submit("cid".to_string(), 2, options).unwrap();
+            test::set_value_transferred::<DefaultEnvironment>(0);
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            contract.claim(0).unwrap();
+            contract.complete(0, 0, "map-0".to_string()).unwrap();
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            contract.claim(0).unwrap();
+            contract.complete(0, 1, "garbage".to_string()).unwrap();
+
+            // The map results can still be disputed, the reduce waits
+            let job = contract.get_job(0).unwrap().unwrap();
+            assert_eq!(job.tasks.len(), 2);
+            assert_eq!(job.reduce_shard, None);
+
+            // The creator disputes the second map result and an auditor rejects it
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            contract.dispute(0, 1, "reason".to_string()).unwrap();
+            contract.grant_role(Role::Auditor, accounts.eve).unwrap();
+            test::set_caller::<DefaultEnvironment>(accounts.eve);
+            contract.resolve_dispute(0, 1, false).unwrap();
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            contract.claim(0).unwrap();
+            contract.complete(0, 1, "map-1".to_string()).unwrap();
+
+            // The reduce starts with the last map payout and takes the final map results as input
+            test::set_block_timestamp::<DefaultEnvironment>(101);
+            assert_eq!(contract.release_payout(0, 0), Ok(100));
+            assert_eq!(contract.get_job(0).unwrap().unwrap().reduce_shard, None);
+            assert_eq!(contract.release_payout(0, 1), Ok(100));
+
+            let job = contract.get_job(0).unwrap().unwrap();
+            assert_eq!(job.reduce_shard, Some(2));
+            assert_eq!(job.get_job_status(), JobStatus::Created);
+            assert_eq!(
+                job.tasks[2].cid_inputs,
+                vec!["map-0".to_string(), "map-1".to_string()]
+            );
+            assert_eq!(
+                contract.claim_first().unwrap().tasks[2].worker,
+                Some(accounts.bob)
+            );
         }
 
-        /// Complete a job task (Backend endpoint)
-        /// The job is updated with the status Completed
-        /// The job is updated with the updated_at timestamp
-        /// The job is updated with the result content id
-        #[ink(message)]
-        pub fn complete(
-            &mut self,
-            job_id: u32,
-            task_id: u32,
-            cid_result: String,
-        ) -> Result<(), Error> {
-            let caller = self.env().caller();
-            let mut job = self.jobs.get(job_id).ok_or(Error::UpdateFailed)?;
-            let task = job
-                .tasks
-                .get_mut(task_id as usize)
-                .ok_or(Error::UpdateFailed)?;
+        /// Test that a map-reduce job cancelled while its map results wait out their challenge period never reduces
+        #[ink::test]
+        fn map_reduce_cancel() {
+            let (mut contract, accounts) = setup();
+            assert_eq!(contract.set_challenge_period(100), Ok(()));
+            let options = JobOptions {
+                kind: JobKind::MapReduce,
+                ..Default::default()
+            };
+            test::set_value_transferred::<DefaultEnvironment>(300);
+            contract.submit("cid".to_string(), 2, options).unwrap();
+            test::set_value_transferred::<DefaultEnvironment>(0);
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            contract.claim(0).unwrap();
+            contract.complete(0, 0, "map-0".to_string()).unwrap();
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            contract.claim(0).unwrap();
+            contract.complete(0, 1, "map-1".to_string()).unwrap();
+
+            // The creator gets the reduce share back, the map results stay owed
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            let balance = test::get_account_balance::<DefaultEnvironment>(accounts.alice).unwrap();
+            contract.cancel(0).unwrap();
+            assert_eq!(
+                test::get_account_balance::<DefaultEnvironment>(accounts.alice).unwrap(),
+                balance + 100
+            );
+            let job = contract.get_job(0).unwrap().unwrap();
+            assert_eq!(job.get_job_status(), JobStatus::Failed);
+            assert_eq!(job.escrow, 200);
+
+            // Releasing the map payouts does not start the reduce
+            test::set_block_timestamp::<DefaultEnvironment>(101);
+            assert_eq!(contract.release_payout(0, 0), Ok(100));
+            assert_eq!(contract.release_payout(0, 1), Ok(100));
+
+            let job = contract.get_job(0).unwrap().unwrap();
+            assert_eq!(job.get_job_status(), JobStatus::Failed);
+            assert_eq!(job.reduce_shard, None);
+            assert_eq!(job.tasks.len(), 2);
+            assert_eq!(job.escrow, 0);
+            assert_eq!(contract.cancel(0), Err(Error::UpdateFailed));
+        }
 
-            // If task not owned by caller, return error
-            if task.worker != Some(caller) {
-                return Err(Error::NotWorker);
-            }
+        /// Test that results wait out the challenge period and disputes go to an auditor
+        #[ink::test]
+        fn dispute_flow() {
+            let (mut contract, accounts) = setup();
+            assert_eq!(contract.set_challenge_period(100), Ok(()));
+
+            test::set_value_transferred::<DefaultEnvironment>(200);
+            contract
+                .submit("cid".to_string(), 2, JobOptions::default())
+                .unwrap();
+            test::set_value_transferred::<DefaultEnvironment>(0);
+            let options = JobOptions {
+                parents: vec![0],
+                ..Default::default()
+            };
+            contract.submit("cid".to_string(), 1, options).unwrap();
 
-            // If task not in valid in progress state, return error
-            if task.status != JobStatus::InProgress {
-                return Err(Error::UpdateFailed);
-            }
+            let bob = test::get_account_balance::<DefaultEnvironment>(accounts.bob).unwrap();
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            contract.claim(0).unwrap();
+            contract.complete(0, 0, "result-0".to_string()).unwrap();
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            contract.claim(0).unwrap();
+            contract.complete(0, 1, "garbage".to_string()).unwrap();
 
-            // Update task status
-            task.status = JobStatus::Completed;
+            // Nothing is paid during the challenge period
+            assert_eq!(
+                test::get_account_balance::<DefaultEnvironment>(accounts.bob).unwrap(),
+                bob
+            );
+            assert_eq!(contract.release_payout(0, 0), Err(Error::ChallengeActive));
 
-            // Update job completed_at timestamp if all tasks are completed
-            let completed_task_count = job
-                .tasks
-                .iter()
-                .filter(|task| task.status == JobStatus::Completed)
-                .count();
-            if completed_task_count == job.tasks.len() {
-                job.completed_at = self.env().block_timestamp().into();
-            }
+            // The creator disputes the second result
+            assert_eq!(
+                contract.dispute(0, 1, "reason".to_string()),
+                Err(Error::NotOwner)
+            );
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.dispute(0, 1, "reason".to_string()), Ok(()));
 
-            // Update job with result content id
-            job.updated_at = self.env().block_timestamp().into();
-            job.cid_results = Some(vec![cid_result.clone()]);
+            // Every task has its result, so there is nothing left to cancel
+            assert_eq!(contract.cancel(0), Err(Error::ChallengeActive));
 
-            // Update job with the updated task
-            self.jobs.set(job_id, &job).unwrap();
+            // Undisputed results are paid once the period has passed
+            test::set_block_timestamp::<DefaultEnvironment>(101);
+            assert_eq!(
+                contract.dispute(0, 0, "reason".to_string()),
+                Err(Error::ChallengeClosed)
+            );
+            assert_eq!(contract.release_payout(0, 0), Ok(100));
+            assert_eq!(contract.release_payout(0, 0), Err(Error::NoPayout));
+            assert_eq!(contract.release_payout(0, 1), Err(Error::Disputed));
+            assert_eq!(
+                test::get_account_balance::<DefaultEnvironment>(accounts.bob).unwrap(),
+                bob + 100
+            );
 
-            // Emit JobCompleted event
-            self.env().emit_event(JobCompleted {
-                creator: caller,
-                job_id,
-                task_id,
-            });
+            // An auditor rejects the disputed result, the task reopens and the worker is slashed
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                contract.resolve_dispute(0, 1, false),
+                Err(Error::MissingRole)
+            );
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            contract.grant_role(Role::Auditor, accounts.eve).unwrap();
+            test::set_caller::<DefaultEnvironment>(accounts.eve);
+            assert_eq!(contract.resolve_dispute(0, 1, false), Ok(()));
+
+            let job = contract.get_job(0).unwrap().unwrap();
+            assert_eq!(job.tasks[1].status, JobStatus::Created);
+            assert_eq!(job.tasks[1].worker, None);
+            assert_eq!(job.escrow, 100);
+            assert!(contract.get_worker(accounts.charlie).unwrap().stake < DEFAULT_MIN_STAKE);
+            assert_eq!(contract.get_worker_stats(accounts.charlie).rejected, 1);
+
+            // The job only completes and releases its dependent once every payout is released
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            contract.claim(0).unwrap();
+            contract.complete(0, 1, "result-1".to_string()).unwrap();
+            let job = contract.get_job(0).unwrap().unwrap();
+            assert_eq!(job.get_job_status(), JobStatus::InProgress);
+            assert_eq!(job.cid_results, None);
+            assert_eq!(contract.get_job(1).unwrap().unwrap().pending_parents, 1);
+
+            test::set_block_timestamp::<DefaultEnvironment>(202);
+            assert_eq!(contract.release_payout(0, 1), Ok(100));
+            let job = contract.get_job(0).unwrap().unwrap();
+            assert_eq!(job.get_job_status(), JobStatus::Completed);
+            assert_eq!(
+                job.cid_results,
+                Some(vec!["result-0".to_string(), "result-1".to_string()])
+            );
+            assert_eq!(contract.get_job(1).unwrap().unwrap().pending_parents, 0);
+        }
 
-            Ok(())
+        /// Test that job listings are paginated
+        #[ink::test]
+        fn paginated_listing() {
+            let (mut contract, accounts) = setup();
+            for _ in 0..3 {
+                contract
+                    .submit("cid".to_string(), 1, JobOptions::default())
+                    .unwrap();
+            }
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            contract
+                .submit("cid".to_string(), 2, JobOptions::default())
+                .unwrap();
+
+            let page = contract.get_jobs(0, 2);
+            assert_eq!(page.jobs.len(), 2);
+            assert_eq!(page.next, Some(2));
+
+            let page = contract.get_jobs(2, 2);
+            assert_eq!(page.jobs.len(), 2);
+            assert_eq!(page.jobs[1].task_count, 2);
+            assert_eq!(page.next, None);
+
+            // Creator listings only hold the creator's own jobs
+            let page = contract.get_jobs_by_creator(None, 0, 3);
+            assert_eq!(page.jobs.len(), 1);
+            assert_eq!(page.jobs[0].id, 3);
+            assert_eq!(page.next, None);
+
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            let page = contract.get_jobs_by_creator(None, 0, 2);
+            assert_eq!(page.jobs.len(), 2);
+            assert_eq!(page.next, Some(2));
         }
 
-        /// Cancel entire job (UI endpoint)
-        /// Owner of the job can cancel the job
-        #[ink(message)]
-        pub fn cancel(&mut self, job_id: u32) -> Result<(), Error> {
-            let caller = self.env().caller();
-            let mut job = self.jobs.get(job_id).ok_or(Error::NoJobs)?;
+        /// Test that worker listings follow claims and completions
+        #[ink::test]
+        fn worker_index() {
+            let (mut contract, accounts) = setup();
+            contract
+                .submit("cid".to_string(), 2, JobOptions::default())
+                .unwrap();
+            contract
+                .submit("cid".to_string(), 1, JobOptions::default())
+                .unwrap();
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            contract.claim(0).unwrap();
+            assert_eq!(contract.claim(1), Err(Error::CallerBusy));
+
+            // Completing the task frees bob for another claim
+            contract.complete(0, 0, "result".to_string()).unwrap();
+            contract.claim(0).unwrap();
+            contract.complete(0, 1, "result".to_string()).unwrap();
+            contract.claim(1).unwrap();
+
+            let page = contract.get_jobs_by_worker(Some(JobStatus::Completed), 0, 10);
+            assert_eq!(page.jobs.len(), 1);
+            assert_eq!(page.jobs[0].id, 0);
+
+            let page = contract.get_jobs_by_worker(None, 0, 10);
+            assert_eq!(page.jobs.len(), 1);
+            assert_eq!(page.jobs[0].id, 1);
+
+            // Cancelling the job releases bob
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            contract.cancel(1).unwrap();
+            assert!(!contract.has_tasks(accounts.bob));
+        }
 
-            // If caller is not the owner of the job, return error
-            if job.creator != caller {
-                return Err(Error::NotOwner);
+        /// Test that claim_first hands out jobs from the open job queue
+        #[ink::test]
+        fn open_job_queue() {
+            let (mut contract, accounts) = setup();
+            for _ in 0..3 {
+                contract
+                    .submit("cid".to_string(), 1, JobOptions::default())
+                    .unwrap();
             }
 
-            // Set all tasks to failed
-            for task in job.tasks.iter_mut() {
-                task.status = JobStatus::Failed;
-            }
+            // Cancelled job leaves the queue
+            let queue = contract.get_job(0).unwrap().unwrap().queue() as usize;
+            contract.cancel(0).unwrap();
+            assert_eq!(contract.open_head[queue], 1);
 
-            // Update job with the status Failed
-            job.updated_at = self.env().block_timestamp().into();
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.claim_first().map(|job| job.id), Ok(1));
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            assert_eq!(contract.claim_first().map(|job| job.id), Ok(2));
+            assert_eq!(contract.open_head, contract.open_tail);
 
-            // Update job with the updated task
-            self.jobs.set(job_id, &job).unwrap();
+            // Failed task puts its job back in the queue
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            contract.fail(1, 0).unwrap();
 
-            Ok(())
-        }
+            register(&mut contract, accounts.eve);
+            assert_eq!(contract.claim_first().map(|job| job.id), Ok(1));
+            assert_eq!(contract.claim_first(), Err(Error::CallerBusy));
 
-        /// Fetch Job by ID (General purpose endpoint)
-        /// Returns the job with the job id
-        /// Returns None if the job id is not found
-        #[ink(message)]
-        pub fn get_job(&self, job_id: u32) -> Result<Option<Job>, Error> {
-            Ok(self.jobs.get(job_id))
+            register(&mut contract, accounts.frank);
+            assert_eq!(contract.claim_first(), Err(Error::NoJobs));
         }
 
-        /// Fetch all Jobs
-        /// Returns a list of all jobs
-        /// Returns None if there are no jobs
-        #[ink(message)]
-        pub fn get_jobs(&self) -> Option<Vec<Job>> {
-            let job_count = self.jobs.len();
-            if job_count == 0 {
-                None
-            } else {
-                let mut job_listing: Vec<Job> = Vec::new();
-                for i in 0..job_count {
-                    if let Some(job) = self.jobs.get(i) {
-                        job_listing.push(job);
-                    }
-                }
-                Some(job_listing)
-            }
+        /// Test that claim_first prefers higher priority jobs and ages waiting ones
+        #[ink::test]
+        fn priority_scheduling() {
+            let (mut contract, accounts) = setup();
+            let priority = |priority| JobOptions {
+                priority,
+                ..Default::default()
+            };
+
+            contract
+                .submit("cid".to_string(), 1, priority(Priority::Low))
+                .unwrap();
+            contract
+                .submit("cid".to_string(), 1, priority(Priority::Normal))
+                .unwrap();
+            contract
+                .submit("cid".to_string(), 1, priority(Priority::Urgent))
+                .unwrap();
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.claim_first().map(|job| job.id), Ok(2));
+
+            // After three aging periods the low priority job catches up with a new urgent job
+            // and wins the tie by age, the normal job is already ahead of both
+            test::set_block_timestamp::<DefaultEnvironment>(PRIORITY_AGING * 3);
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            contract
+                .submit("cid".to_string(), 1, priority(Priority::Urgent))
+                .unwrap();
+
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            assert_eq!(contract.claim_first().map(|job| job.id), Ok(1));
+
+            register(&mut contract, accounts.eve);
+            assert_eq!(contract.claim_first().map(|job| job.id), Ok(0));
+
+            register(&mut contract, accounts.frank);
+            assert_eq!(contract.claim_first().map(|job| job.id), Ok(3));
         }
 
-        /// Fetch all Jobs by Creator (General purpose endpoint)
-        /// Returns a list of all jobs created by the caller
-        /// Filter by job status if provided
-        /// Returns None if there are no jobs created by the caller
-        #[ink(message)]
-        pub fn get_jobs_by_creator(&self, job_status: Option<JobStatus>) -> Option<Vec<Job>> {
-            let caller = self.env().caller();
-            let job_count = self.jobs.len();
-            if job_count == 0 {
-                None
-            } else {
-                let mut job_listing: Vec<Job> = Vec::new();
-                for i in 0..job_count {
-                    if let Ok(job) = self.jobs.try_get(i)? {
-                        if job.creator == caller {
-                            if let Some(status) = job_status {
-                                if job.get_job_status() == status {
-                                    job_listing.push(job.clone());
-                                }
-                            } else {
-                                job_listing.push(job.clone());
-                            }
-                        }
-                    }
-                }
-                Some(job_listing)
-            }
+        /// Test that jobs are only handed to workers able to run them
+        #[ink::test]
+        fn capability_matching() {
+            let (mut contract, accounts) = setup();
+            let requirements = JobRequirements {
+                runtime: Runtime::Petals,
+                min_gpu_memory: 24_000,
+                regions: vec!["eu".to_string()],
+                ..Default::default()
+            };
+            let options = JobOptions {
+                requirements: Some(requirements),
+                ..Default::default()
+            };
+            contract.submit("cid".to_string(), 1, options).unwrap();
+            contract
+                .submit("cid".to_string(), 1, JobOptions::default())
+                .unwrap();
+
+            // Bob declared no capabilities, only the unconstrained job is for him
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.claim(0), Err(Error::RequirementsNotMet));
+            assert_eq!(contract.claim_first().map(|job| job.id), Ok(1));
+
+            // Eve runs Petals on a large GPU in the right region
+            test::set_caller::<DefaultEnvironment>(accounts.eve);
+            test::set_value_transferred::<DefaultEnvironment>(DEFAULT_MIN_STAKE);
+            contract
+                .register_worker(Capabilities {
+                    runtimes: vec![Runtime::Docker, Runtime::Petals],
+                    gpu_memory: 40_000,
+                    region: Some("eu".to_string()),
+                    ..Default::default()
+                })
+                .unwrap();
+            test::set_value_transferred::<DefaultEnvironment>(0);
+
+            assert_eq!(contract.claim_first().map(|job| job.id), Ok(0));
         }
 
-        /// Fetch all Jobs by Worker (General purpose endpoint)
-        /// Returns a list of all jobs with tasks claimed by the caller
-        /// Filter by job status if provided
-        /// Returns None if there are no jobs claimed by the caller
-        #[ink(message)]
-        pub fn get_jobs_by_worker(&self, job_status: Option<JobStatus>) -> Option<Vec<Job>> {
-            let caller = self.env().caller();
+        /// Test that jobs for other runtimes don't hide a job the worker can run
+        #[ink::test]
+        fn runtime_queues() {
+            let (mut contract, accounts) = setup();
+            let options = |runtime| JobOptions {
+                requirements: Some(JobRequirements {
+                    runtime,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            };
 
-            let mut job_listing: Vec<Job> = Vec::new();
-            for i in 0..self.jobs.len() {
-                if let Ok(job) = self.jobs.try_get(i)? {
-                    if job.has_worker(caller, job_status) {
-                        job_listing.push(job.clone());
-                    }
-                }
+            // More Docker jobs than claim_first looks at in one queue
+            for _ in 0..=MAX_QUEUE_SCAN {
+                contract
+                    .submit("cid".to_string(), 1, options(Runtime::Docker))
+                    .unwrap();
             }
-
-            Some(job_listing)
+            let job_id = contract
+                .submit("cid".to_string(), 1, options(Runtime::Petals))
+                .unwrap();
+
+            // Eve only runs Petals
+            test::set_caller::<DefaultEnvironment>(accounts.eve);
+            test::set_value_transferred::<DefaultEnvironment>(DEFAULT_MIN_STAKE);
+            contract
+                .register_worker(Capabilities {
+                    runtimes: vec![Runtime::Petals],
+                    ..Default::default()
+                })
+                .unwrap();
+            test::set_value_transferred::<DefaultEnvironment>(0);
+            assert_eq!(contract.claim_first().map(|job| job.id), Ok(job_id));
+
+            // Bob declared no runtime, none of the jobs are for him
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.claim_first(), Err(Error::NoJobs));
         }
-    }
-
-    /// Unit tests in Rust are normally defined within such a `#[cfg(test)]`
-    /// module and test functions are marked with a `#[test]` attribute.
-    /// The below code is technically just normal Rust code.
-    #[cfg(test)]
-    mod tests {
-        use super::*;
 
-        /// We test if the default constructor does its job.
+        /// Test that only the owner can change settings and hand over the contract
         #[ink::test]
-        fn deploy_contact_default() {
-            let contract = NocturneJob::default();
-            assert_eq!(contract.max_retries, 3);
-            assert_eq!(contract.max_tasks, 15);
-            assert_eq!(contract.jobs.len(), 0);
+        fn owner_config() {
+            let (mut contract, accounts) = setup();
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.set_max_tasks(2), Err(Error::MissingRole));
+
+            // Submit is validated against the stored limit
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.set_max_tasks(0), Err(Error::InvalidConfig));
+            assert_eq!(contract.set_max_tasks(2), Ok(()));
+            assert_eq!(
+                contract.submit("cid".to_string(), 3, JobOptions::default()),
+                Err(Error::SubmitFailed)
+            );
+            assert!(contract
+                .submit("cid".to_string(), 2, JobOptions::default())
+                .is_ok());
+
+            // Ownership moves to bob, who then gives it up
+            assert_eq!(contract.transfer_ownership(accounts.bob), Ok(()));
+            assert_eq!(contract.set_max_retries(5), Err(Error::MissingRole));
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.set_max_retries(5), Ok(()));
+            assert_eq!(contract.renounce_ownership(), Ok(()));
+            assert_eq!(contract.get_owner(), None);
+            assert_eq!(contract.set_max_retries(1), Err(Error::MissingRole));
         }
 
-        /// Test Job status transitions and reporting
+        /// Test that roles gate operators and, in permissioned mode, workers
         #[ink::test]
-        fn job_status() {
-            let mut job = Job::new(account_id::<DefaultEnvironment>(), "cid".to_string(), 3);
-
-            // Job is created
+        fn role_access() {
+            let (mut contract, accounts) = setup();
+            contract
+                .submit("cid".to_string(), 1, JobOptions::default())
+                .unwrap();
+
+            // Operators adjust limits and pause without owning the contract
+            test::set_caller::<DefaultEnvironment>(accounts.eve);
             assert_eq!(
-                job.get_job_status(),
-                JobStatus::Created,
-                "Job is created if all tasks are created ({:?}|{:?}|{:?})",
-                job.tasks[0].status,
-                job.tasks[1].status,
-                job.tasks[2].status
+                contract.grant_role(Role::Operator, accounts.eve),
+                Err(Error::NotContractOwner)
             );
+            assert_eq!(contract.set_max_tasks(5), Err(Error::MissingRole));
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.grant_role(Role::Operator, accounts.eve), Ok(()));
+            assert!(contract.has_role(Role::Operator, accounts.eve));
+            test::set_caller::<DefaultEnvironment>(accounts.eve);
+            assert_eq!(contract.set_max_tasks(5), Ok(()));
+            assert_eq!(contract.pause(), Ok(()));
+            assert_eq!(contract.unpause(), Ok(()));
+
+            // Permissioned mode only lets allowlisted workers claim
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.set_permissioned(true), Ok(()));
+            assert_eq!(contract.grant_role(Role::Worker, accounts.charlie), Ok(()));
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.claim(0).err(), Some(Error::MissingRole));
+            assert_eq!(contract.reclaim_expired(0), Err(Error::MissingRole));
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            assert!(contract.claim(0).is_ok());
+
+            // Revoking the role stops further claims
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.revoke_role(Role::Worker, accounts.charlie), Ok(()));
+            assert!(!contract.has_role(Role::Worker, accounts.charlie));
+        }
 
-            // Job is in progress
-            job.tasks[0].status = JobStatus::InProgress;
-            job.tasks[1].status = JobStatus::Created;
-            job.tasks[2].status = JobStatus::Completed;
+        /// Test that migrating a contract already on the current layout is a no-op
+        #[ink::test]
+        fn storage_migration() {
+            let (mut contract, accounts) = setup();
+            contract
+                .submit("cid".to_string(), 1, JobOptions::default())
+                .unwrap();
+            assert_eq!(contract.get_storage_version(), STORAGE_VERSION);
+
+            assert_eq!(contract.migrate(10), Ok(true));
+            assert_eq!(contract.get_job(0).unwrap().unwrap().tasks.len(), 1);
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.migrate(10), Err(Error::NotContractOwner));
             assert_eq!(
-                job.get_job_status(),
-                JobStatus::InProgress,
-                "Job is in progress if any task is in progress ({:?}|{:?}|{:?})",
-                job.tasks[0].status,
-                job.tasks[1].status,
-                job.tasks[2].status
+                contract.upgrade(Hash::from([1; 32])),
+                Err(Error::NotContractOwner)
             );
+        }
 
-            // Job is failed due to task failure
-            job.tasks[0].status = JobStatus::Failed;
-            job.tasks[1].status = JobStatus::InProgress;
-            job.tasks[2].status = JobStatus::Created;
+        /// Test that jobs stored in the previous layout are converted and rewritten by migrate
+        #[ink::test]
+        fn migrate_v1_jobs() {
+            let (mut contract, accounts) = setup();
+
+            // A job written before the upgrade, one of its two tasks already completed
+            let task = |id, status, worker, cid_result: Option<&str>| TaskV1 {
+                id,
+                worker,
+                status,
+                retries: 0,
+                created_at: 0,
+                updated_at: None,
+                completed_at: None,
+                reward: 50,
+                lease_expires_at: None,
+                cid_result: cid_result.map(String::from),
+            };
+            let job = JobV1 {
+                id: 0,
+                creator: accounts.alice,
+                cid_manifest: "cid".to_string(),
+                cid_results: None,
+                tip: Some(100),
+                escrow: 50,
+                tasks: vec![
+                    task(
+                        0,
+                        JobStatus::Completed,
+                        Some(accounts.charlie),
+                        Some("result-0"),
+                    ),
+                    task(1, JobStatus::Created, None, None),
+                ],
+                created_at: 0,
+                updated_at: None,
+                completed_at: None,
+                deadline: None,
+                min_reputation: 0,
+                priority: Priority::Normal,
+                requirements: None,
+            };
+            contract.jobs.insert(0, &JobRecord::V1(job));
+            contract.next_job_id = 1;
+            contract.storage_version = 1;
+
+            // Old records are readable before the migration has run
+            let job = contract.get_job(0).unwrap().unwrap();
+            assert!(job.tasks[0].paid);
+            assert_eq!(job.tasks[1].shard, 1);
+            assert_eq!(job.replicas, 1);
+
+            assert_eq!(contract.migrate(10), Ok(true));
+            assert_eq!(contract.get_storage_version(), STORAGE_VERSION);
+            assert!(contract.jobs.get(0).unwrap().is_current());
+
+            // The migrated job keeps running
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            contract.claim(0).unwrap();
+            contract.complete(0, 1, "result".to_string()).unwrap();
+            let job = contract.get_job(0).unwrap().unwrap();
+            assert_eq!(job.get_job_status(), JobStatus::Completed);
+            assert_eq!(job.escrow, 0);
             assert_eq!(
-                job.get_job_status(),
-                JobStatus::Failed,
-                "Job is failed if any task is failed ({:?}|{:?}|{:?})",
-                job.tasks[0].status,
-                job.tasks[1].status,
-                job.tasks[2].status
+                job.cid_results,
+                Some(vec!["result-0".to_string(), "result".to_string()])
             );
+        }
 
-            // Job is completed
-            job.tasks[0].status = JobStatus::Completed;
-            job.tasks[1].status = JobStatus::Completed;
-            job.tasks[2].status = JobStatus::Completed;
+        /// Test that pausing stops new work but lets running work unwind
+        #[ink::test]
+        fn pause_stops_new_work() {
+            let (mut contract, accounts) = setup();
+            contract
+                .submit("cid".to_string(), 2, JobOptions::default())
+                .unwrap();
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            contract.claim(0).unwrap();
+
+            // Only the owner or the guardian may pause
+            assert_eq!(contract.pause(), Err(Error::NotGuardian));
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            contract.set_guardian(Some(accounts.eve)).unwrap();
+            test::set_caller::<DefaultEnvironment>(accounts.eve);
+            assert_eq!(contract.pause(), Ok(()));
+
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
             assert_eq!(
-                job.get_job_status(),
-                JobStatus::Completed,
-                "Job is completed if all tasks are completed ({:?}|{:?}|{:?})",
-                job.tasks[0].status,
-                job.tasks[1].status,
-                job.tasks[2].status
+                contract.submit("cid".to_string(), 1, JobOptions::default()),
+                Err(Error::Paused)
             );
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            assert_eq!(contract.claim(0), Err(Error::Paused));
+            assert_eq!(contract.claim_first(), Err(Error::Paused));
+
+            // Running work can still complete and jobs can be cancelled
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.complete(0, 0, "result".to_string()), Ok(()));
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.cancel(0), Ok(()));
+
+            assert_eq!(contract.unpause(), Ok(()));
+            assert!(!contract.is_paused());
         }
     }
 
@@ -808,18 +5386,17 @@ mod nocturne_job {
             let mut call_builder = contract.call_builder::<NocturneJob>();
 
             // Then
-            let submit = call_builder.submit(manifest_cid.clone(), task_count);
+            let submit =
+                call_builder.submit(manifest_cid.clone(), task_count, JobOptions::default());
             let submit_result = client.call(&ink_e2e::bob(), &submit).dry_run().await?;
-            assert!(matches!(submit_result.return_value(), Ok(())));
+            assert!(matches!(submit_result.return_value(), Ok(0)));
 
-            let jobs = call_builder.get_jobs();
+            let jobs = call_builder.get_jobs(0, 10);
             let jobs_result = client.call(&ink_e2e::bob(), &jobs).dry_run().await?;
 
-            if let Some(jobs) = jobs_result.return_value() {
-                assert_eq!(jobs.len(), 1);
-            } else {
-                assert!(false);
-            }
+            let page = jobs_result.return_value();
+            assert_eq!(page.jobs.len(), 1);
+            assert_eq!(page.next, None);
 
             Ok(())
         }